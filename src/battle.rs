@@ -1,30 +1,39 @@
 use std::time::Duration;
 
 use bevy::{
-    asset::HandleId,
     core_pipeline::clear_color::ClearColorConfig,
     gltf::Gltf,
+    pbr::{NotShadowCaster, NotShadowReceiver},
     prelude::*,
     render::{camera::ScalingMode, view::RenderLayers},
+    scene::SceneInstance,
 };
 use bevy_tweening::{
-    lens::TransformPositionLens, Animator, Delay, EaseFunction, Tween, TweeningType,
+    lens::{TransformPositionLens, TransformScaleLens},
+    Animator, Delay, EaseFunction, Sequence, Tween, Tweenable, TweeningType,
 };
 use iyes_loopless::prelude::*;
-use strum::{EnumCount, IntoEnumIterator};
-use strum_macros::{Display, EnumCount, EnumIter, EnumVariantNames};
 
 use crate::{
     board::{
-        BoardPrefab, BoardState, Element, Match, Tile, BETWEEN_MATCH_DELAY, MATCH_START_DELAY,
+        BoardPrefab, BoardSettings, BoardState, Element, Gem, Match, Tile, BETWEEN_MATCH_DELAY,
+        DEFAULT_MOVE_TIME, MATCH_START_DELAY, MIN_MOVE_TIME,
     },
     cards::{CardsPrefab, CardsState},
+    combat::{
+        self, CombatTarget, DamageDealt, EnemyKilled, EnemyState, Field, Healed, MatchedGems,
+        Shielded,
+    },
+    enemies::{BoardHazard, EnemyAnimation, EnemyDefinition},
+    environments::EnvironmentLayout,
+    main_state::Difficulty,
     particles::ParticleEmitter,
     player::{Player, Spell},
     prefab::*,
     transitions::{FadeScreenPrefab, TransitionDirection, TransitionEnd},
     utils::{
-        go_to, DelayedDespawn, DespawnReason, Loading, ProgressBar, ProgressBarPrefab, WorldCursor,
+        go_to, square_mesh, white_standard_material, DelayedDespawn, DespawnReason, ProgressBar,
+        ProgressBarPrefab, WorldCursor,
     },
 };
 
@@ -37,13 +46,14 @@ impl Plugin for BattlePlugin {
             .insert_resource(BattleResources {
                 root_entities: vec![],
             })
-            .add_startup_system(load_enemy_models)
             .add_system(play_idle_animation)
+            .add_system(tick_procedural_animations)
             .add_system(find_enemy_animations)
             .add_system(build_enemy_animators)
             .add_system(remove_unlit_materials)
             .add_system(update_enemy_health_bar)
             .add_system(update_player_health_bar)
+            .add_system(update_boss_ui)
             .add_system(stop_board.run_not_in_state(BattleState::PlayerTurn))
             .add_system_set(
                 ConditionSet::new()
@@ -53,6 +63,10 @@ impl Plugin for BattlePlugin {
             )
             .add_enter_system(BattleState::PlayerTurn, start_player_turn)
             .add_enter_system(BattleState::PlayerTurn, go_to(CardsState::Draw))
+            .add_enter_system(
+                CardsState::End,
+                cast_spell.run_in_state(BattleState::PlayerTurn),
+            )
             .add_enter_system(
                 CardsState::End,
                 go_to(BoardState::Ready).run_in_state(BattleState::PlayerTurn),
@@ -126,15 +140,6 @@ fn send_cleanup_event(mut events: EventWriter<BattleCleanedUp>) {
     events.send(BattleCleanedUp);
 }
 
-fn load_enemy_models(asset_server: Res<AssetServer>, mut loading: ResMut<Loading>) {
-    let models: Vec<_> = EnemyKind::gltf_paths()
-        .into_iter()
-        .map(|path| asset_server.load_untyped(&path))
-        .collect();
-
-    loading.assets.extend(models);
-}
-
 fn stop_board(mut commands: Commands, state: Res<CurrentState<BoardState>>) {
     if state.0 != BoardState::None {
         commands.insert_resource(NextState(BoardState::None));
@@ -143,16 +148,148 @@ fn stop_board(mut commands: Commands, state: Res<CurrentState<BoardState>>) {
 
 #[derive(Component)]
 struct EnemyAnimations {
-    idle: Handle<AnimationClip>,
-    hurt: Handle<AnimationClip>,
+    idle: Option<Handle<AnimationClip>>,
+    hurt: Option<Handle<AnimationClip>>,
+    attack: Option<Handle<AnimationClip>>,
     death: Option<Handle<AnimationClip>>,
-    attack: Handle<AnimationClip>,
+}
+
+impl EnemyAnimations {
+    fn get(&self, animation: EnemyAnimation) -> Option<&Handle<AnimationClip>> {
+        match animation {
+            EnemyAnimation::Idle => self.idle.as_ref(),
+            EnemyAnimation::Hurt => self.hurt.as_ref(),
+            EnemyAnimation::Attack => self.attack.as_ref(),
+            EnemyAnimation::Death => self.death.as_ref(),
+        }
+    }
 }
 
 #[derive(Component)]
 struct EnemyAnimator {
-    animation_player: Entity,
+    /// Models without any animation do not have an animation player.
+    animation_player: Option<Entity>,
     current_animation: Option<Handle<AnimationClip>>,
+    /// Runs while a procedural animation is played in place of a missing clip.
+    procedural: Option<Timer>,
+    /// Where procedural animations start from and return to.
+    rest: Transform,
+}
+
+impl EnemyAnimator {
+    fn is_idle(&self, animations: &EnemyAnimations) -> bool {
+        self.procedural.is_none() && self.current_animation == animations.idle
+    }
+
+    /// Plays the model's clip for the animation, or a procedural animation if the model lacks one.
+    fn play(
+        &mut self,
+        entity: Entity,
+        animation: EnemyAnimation,
+        animations: &EnemyAnimations,
+        animation_players: &mut Query<&mut AnimationPlayer>,
+        commands: &mut Commands,
+    ) {
+        let clip = animations.get(animation);
+
+        if let (Some(clip), Some(animation_player)) = (clip, self.animation_player) {
+            let mut animation_player = animation_players.get_mut(animation_player).unwrap();
+
+            animation_player.play(clip.clone());
+            self.current_animation = Some(clip.clone());
+        } else {
+            let (sequence, duration) = procedural_animation(animation, self.rest);
+
+            commands.entity(entity).insert(Animator::new(sequence));
+            self.procedural = Some(Timer::new(duration, false));
+        }
+    }
+
+    fn duration(&self, animations: &Assets<AnimationClip>) -> f32 {
+        match &self.procedural {
+            Some(timer) => timer.duration().as_secs_f32(),
+            None => self
+                .current_animation
+                .as_ref()
+                .and_then(|x| animations.get(x))
+                .map(|x| x.duration())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn procedural_animation(
+    animation: EnemyAnimation,
+    rest: Transform,
+) -> (Sequence<Transform>, Duration) {
+    fn move_to(start: Vec3, end: Vec3, seconds: f32, ease: EaseFunction) -> Tween<Transform> {
+        Tween::new(
+            ease,
+            TweeningType::Once,
+            Duration::from_secs_f32(seconds),
+            TransformPositionLens { start, end },
+        )
+    }
+
+    let position = rest.translation;
+
+    let tweens = match animation {
+        EnemyAnimation::Idle => vec![],
+        EnemyAnimation::Hurt => {
+            let shake = Vec3::X * 0.15;
+            let ease = EaseFunction::SineInOut;
+
+            vec![
+                move_to(position, position + shake, 0.06, ease),
+                move_to(position + shake, position - shake, 0.12, ease),
+                move_to(position - shake, position + shake * 0.5, 0.1, ease),
+                move_to(position + shake * 0.5, position, 0.08, ease),
+            ]
+        }
+        EnemyAnimation::Attack => {
+            // towards the camera
+            let lunge = position + Vec3::Z;
+
+            vec![
+                move_to(
+                    position,
+                    position - Vec3::Z * 0.2,
+                    0.2,
+                    EaseFunction::QuadraticOut,
+                ),
+                move_to(
+                    position - Vec3::Z * 0.2,
+                    lunge,
+                    0.12,
+                    EaseFunction::QuadraticIn,
+                ),
+                move_to(lunge, position, 0.35, EaseFunction::QuadraticInOut),
+            ]
+        }
+        EnemyAnimation::Death => vec![Tween::new(
+            EaseFunction::BackIn,
+            TweeningType::Once,
+            Duration::from_secs_f32(0.6),
+            TransformScaleLens {
+                start: rest.scale,
+                end: Vec3::ZERO,
+            },
+        )],
+    };
+
+    let duration = tweens.iter().map(|x| x.duration()).sum();
+
+    (Sequence::new(tweens), duration)
+}
+
+fn tick_procedural_animations(mut animators: Query<&mut EnemyAnimator>, time: Res<Time>) {
+    for mut animator in &mut animators {
+        if let Some(timer) = &mut animator.procedural {
+            if timer.tick(time.delta()).finished() {
+                animator.procedural = None;
+            }
+        }
+    }
 }
 
 fn find_enemy_animations(
@@ -161,36 +298,24 @@ fn find_enemy_animations(
     gltfs: Res<Assets<Gltf>>,
 ) {
     for (entity, enemy) in &enemies {
-        if let Some(gltf) = gltfs.get(&enemy.kind.gltf_handle()) {
-            let idle = ["Idle", "Flying"]
-                .iter()
-                .find_map(|name| gltf.named_animations.get(*name));
-            let hurt = ["HitRecieve"]
-                .iter()
-                .find_map(|name| gltf.named_animations.get(*name));
-            let death = ["Death"]
-                .iter()
-                .find_map(|name| gltf.named_animations.get(*name));
-            let attack = ["Bite_Front"]
-                .iter()
-                .find_map(|name| gltf.named_animations.get(*name));
-
-            if let (Some(idle), Some(hurt), Some(attack)) = (idle, hurt, attack) {
-                commands.entity(entity).insert(EnemyAnimations {
-                    idle: idle.clone(),
-                    hurt: hurt.clone(),
-                    death: death.cloned(),
-                    attack: attack.clone(),
-                });
-            }
+        if let Some(gltf) = gltfs.get(&enemy.state.definition.gltf_handle()) {
+            let definition = &enemy.state.definition;
+
+            commands.entity(entity).insert(EnemyAnimations {
+                idle: definition.find_animation(gltf, EnemyAnimation::Idle),
+                hurt: definition.find_animation(gltf, EnemyAnimation::Hurt),
+                attack: definition.find_animation(gltf, EnemyAnimation::Attack),
+                death: definition.find_animation(gltf, EnemyAnimation::Death),
+            });
         }
     }
 }
 
 fn build_enemy_animators(
-    enemies: Query<Entity, (With<Enemy>, Without<EnemyAnimator>)>,
+    enemies: Query<(Entity, &Transform, &SceneInstance), (With<Enemy>, Without<EnemyAnimator>)>,
     children: Query<&Children>,
     animations: Query<&AnimationPlayer>,
+    scene_spawner: Res<SceneSpawner>,
     mut commands: Commands,
 ) {
     fn find_animation_player(
@@ -210,11 +335,14 @@ fn build_enemy_animators(
             .find_map(|e| find_animation_player(e, children, animations))
     }
 
-    for entity in &enemies {
-        if let Some(animation_player) = find_animation_player(entity, &children, &animations) {
+    for (entity, transform, scene) in &enemies {
+        // the animation player is only there once the scene has been spawned
+        if scene_spawner.instance_is_ready(**scene) {
             commands.entity(entity).insert(EnemyAnimator {
-                animation_player,
+                animation_player: find_animation_player(entity, &children, &animations),
                 current_animation: None,
+                procedural: None,
+                rest: *transform,
             });
         }
     }
@@ -226,9 +354,10 @@ fn play_idle_animation(
     animations: Res<Assets<AnimationClip>>,
 ) {
     for (enemy_animations, mut animator) in &mut enemies {
-        let mut animation_player = animation_players
-            .get_mut(animator.animation_player)
-            .unwrap();
+        let mut animation_player = match animator.animation_player {
+            Some(entity) => animation_players.get_mut(entity).unwrap(),
+            None => continue,
+        };
 
         // The default animation player is playing by default and never stops even though there is no animation clip.
         // The animation's elapsed time is very unlikely to be a 0.0 unless there is no animation clip.
@@ -247,13 +376,15 @@ fn play_idle_animation(
             .map(|x| animation_player.elapsed() > x.duration())
             .unwrap_or_default();
 
-        if (no_animation || animation_ended)
-            && (animator.current_animation.as_ref() != Some(&enemy_animations.idle))
+        if (no_animation || animation_ended) && animator.current_animation != enemy_animations.idle
         {
-            animator.current_animation = Some(enemy_animations.idle.clone());
-            animation_player
-                .play(enemy_animations.idle.clone())
-                .repeat();
+            animator.current_animation = enemy_animations.idle.clone();
+
+            if let Some(idle) = &enemy_animations.idle {
+                animation_player.play(idle.clone()).repeat();
+            } else {
+                animation_player.pause();
+            }
         }
     }
 }
@@ -267,41 +398,308 @@ fn remove_unlit_materials(mut materials: ResMut<Assets<StandardMaterial>>) {
     }
 }
 
+const BOSS_INTRO_TIME: f32 = 2.5;
+const FIELD_INTRO_TIME: f32 = 2.0;
+
+#[derive(Default)]
+enum IntroStep {
+    #[default]
+    Start,
+    FadeIn,
+    AnnounceField {
+        banner: Entity,
+        timer: Timer,
+    },
+    AnnounceBoss {
+        banner: Entity,
+        timer: Timer,
+    },
+}
+
+#[allow(clippy::too_many_arguments)]
 fn intro(
-    mut started: Local<bool>,
+    mut step: Local<IntroStep>,
     mut events: EventReader<TransitionEnd>,
     mut cameras: Query<&mut Camera, With<BattleCamera>>,
+    mut enemies: Query<(
+        Entity,
+        &Enemy,
+        Option<(&mut EnemyAnimator, &EnemyAnimations)>,
+    )>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+    field: Res<Field>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
     mut commands: Commands,
 ) {
-    if *started {
-        for event in events.iter() {
-            commands.entity(event.transition).despawn_recursive();
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    match &mut *step {
+        IntroStep::Start => {
+            commands.spawn_prefab(FadeScreenPrefab {
+                direction: TransitionDirection::In,
+                color: Color::BLACK,
+                delay: default(),
+                duration: Duration::from_secs(1),
+            });
 
-            commands.insert_resource(NextState(BattleState::PlayerTurn));
+            for mut camera in &mut cameras {
+                camera.is_active = true;
+            }
+
+            *step = IntroStep::FadeIn;
+        }
+        IntroStep::FadeIn => {
+            for event in events.iter() {
+                commands.entity(event.transition).despawn_recursive();
+
+                *step = if field.effects.is_empty() {
+                    announce_boss(
+                        &mut enemies,
+                        &mut animation_players,
+                        font.clone(),
+                        &mut commands,
+                    )
+                } else {
+                    let banner = commands
+                        .spawn_prefab(FieldBannerPrefab {
+                            field: (*field).clone(),
+                            font: font.clone(),
+                        })
+                        .id();
+
+                    IntroStep::AnnounceField {
+                        banner,
+                        timer: Timer::from_seconds(FIELD_INTRO_TIME, false),
+                    }
+                };
+            }
+        }
+        IntroStep::AnnounceField { banner, timer } => {
+            if timer.tick(time.delta()).finished() {
+                commands.entity(*banner).despawn_recursive();
+
+                *step = announce_boss(&mut enemies, &mut animation_players, font, &mut commands);
+            }
+        }
+        IntroStep::AnnounceBoss { banner, timer } => {
+            if timer.tick(time.delta()).finished() {
+                commands.entity(*banner).despawn_recursive();
+                commands.insert_resource(NextState(BattleState::PlayerTurn));
 
-            *started = false;
+                *step = IntroStep::Start;
+            }
+        }
+    }
+}
+
+/// Shows the boss banner if there is a boss, otherwise starts the battle.
+fn announce_boss(
+    enemies: &mut Query<(
+        Entity,
+        &Enemy,
+        Option<(&mut EnemyAnimator, &EnemyAnimations)>,
+    )>,
+    animation_players: &mut Query<&mut AnimationPlayer>,
+    font: Handle<Font>,
+    commands: &mut Commands,
+) -> IntroStep {
+    let boss = enemies
+        .iter_mut()
+        .find(|(_, enemy, _)| enemy.state.definition.boss.is_some());
+
+    if let Some((entity, enemy, animation)) = boss {
+        if let Some((mut animator, animations)) = animation {
+            animator.play(
+                entity,
+                EnemyAnimation::Attack,
+                animations,
+                animation_players,
+                commands,
+            );
+        }
+
+        let banner = commands
+            .spawn_prefab(BossBannerPrefab {
+                definition: enemy.state.definition.clone(),
+                font,
+            })
+            .id();
+
+        IntroStep::AnnounceBoss {
+            banner,
+            timer: Timer::from_seconds(BOSS_INTRO_TIME, false),
         }
     } else {
-        commands.spawn_prefab(FadeScreenPrefab {
-            direction: TransitionDirection::In,
-            color: Color::BLACK,
-            delay: default(),
-            duration: Duration::from_secs(1),
+        commands.insert_resource(NextState(BattleState::PlayerTurn));
+
+        IntroStep::Start
+    }
+}
+
+struct FieldBannerPrefab {
+    field: Field,
+    font: Handle<Font>,
+}
+
+impl Prefab for FieldBannerPrefab {
+    fn construct(self, entity: &mut EntityCommands) {
+        let sections = self.field.effects.iter().flat_map(|effect| {
+            [
+                TextSection {
+                    value: format!("{}\n", effect.name),
+                    style: TextStyle {
+                        font: self.font.clone(),
+                        font_size: 300.0,
+                        color: Color::WHITE,
+                    },
+                },
+                TextSection {
+                    value: format!("{}\n", effect.kind.description()),
+                    style: TextStyle {
+                        font: self.font.clone(),
+                        font_size: 150.0,
+                        color: Color::GOLD,
+                    },
+                },
+            ]
         });
 
-        for mut camera in &mut cameras {
-            camera.is_active = true;
-        }
+        entity
+            .insert_bundle(Text2dBundle {
+                text: Text::from_sections(sections).with_alignment(TextAlignment::CENTER),
+                transform: Transform::from_xyz(0.0, 600.0, 100.0),
+                ..default()
+            })
+            .insert(CARDS_LAYER)
+            .insert(Animator::new(Tween::new(
+                EaseFunction::BackOut,
+                TweeningType::Once,
+                Duration::from_secs_f32(0.5),
+                TransformScaleLens {
+                    start: Vec3::ZERO,
+                    end: Vec3::ONE,
+                },
+            )));
+    }
+}
 
-        *started = true;
+struct BossBannerPrefab {
+    definition: EnemyDefinition,
+    font: Handle<Font>,
+}
+
+impl Prefab for BossBannerPrefab {
+    fn construct(self, entity: &mut EntityCommands) {
+        let title = self
+            .definition
+            .boss
+            .as_ref()
+            .map(|x| x.title.clone())
+            .unwrap_or_default();
+
+        entity
+            .insert_bundle(Text2dBundle {
+                text: Text::from_sections([
+                    TextSection {
+                        value: format!("{}\n", self.definition.name),
+                        style: TextStyle {
+                            font: self.font.clone(),
+                            font_size: 400.0,
+                            color: Color::WHITE,
+                        },
+                    },
+                    TextSection {
+                        value: title,
+                        style: TextStyle {
+                            font: self.font,
+                            font_size: 200.0,
+                            color: Color::ORANGE_RED,
+                        },
+                    },
+                ])
+                .with_alignment(TextAlignment::CENTER),
+                transform: Transform::from_xyz(0.0, 600.0, 100.0),
+                ..default()
+            })
+            .insert(CARDS_LAYER)
+            .insert(Animator::new(Tween::new(
+                EaseFunction::BackOut,
+                TweeningType::Once,
+                Duration::from_secs_f32(0.5),
+                TransformScaleLens {
+                    start: Vec3::ZERO,
+                    end: Vec3::ONE,
+                },
+            )));
     }
 }
 
 #[derive(Default)]
 struct Matches(Vec<Match>);
 
-fn start_player_turn(mut commands: Commands) {
+fn start_player_turn(
+    enemies: Query<&Enemy>,
+    mut gems: Query<&mut Gem>,
+    mut board_settings: ResMut<BoardSettings>,
+    mut player: ResMut<Player>,
+    difficulty: Res<Difficulty>,
+    mut commands: Commands,
+) {
     commands.insert_resource(Matches::default());
+
+    player.decay_shield();
+    player.refill_energy();
+
+    let mut move_time = DEFAULT_MOVE_TIME - difficulty.move_time_penalty();
+    let mut rng = fastrand::Rng::new();
+
+    for hazard in enemies.iter().filter_map(|x| x.state.hazard()) {
+        match hazard {
+            BoardHazard::Corrupt {
+                element,
+                gems: count,
+            } => {
+                let mut candidates: Vec<_> =
+                    gems.iter_mut().filter(|x| x.element != element).collect();
+
+                rng.shuffle(&mut candidates);
+
+                for mut gem in candidates.into_iter().take(count as usize) {
+                    gem.element = element;
+                }
+            }
+            BoardHazard::Haste { seconds } => move_time -= seconds,
+        }
+    }
+
+    board_settings.move_time = move_time.max(MIN_MOVE_TIME);
+}
+
+/// Runs the effects of the merged spell that change the coming move.
+fn cast_spell(
+    mut gems: Query<&mut Gem>,
+    mut board_settings: ResMut<BoardSettings>,
+    mut player: ResMut<Player>,
+) {
+    let cast = match player.active_spell.as_ref() {
+        Some(spell) => combat::spell_cast(spell),
+        None => return,
+    };
+
+    player.extra_draws += cast.draws as usize;
+    board_settings.move_time = (board_settings.move_time + cast.move_time).max(MIN_MOVE_TIME);
+
+    let rng = fastrand::Rng::new();
+    for (from, to, count) in cast.conversions {
+        let mut candidates: Vec<_> = gems.iter_mut().filter(|x| x.element == from).collect();
+
+        rng.shuffle(&mut candidates);
+
+        for mut gem in candidates.into_iter().take(count as usize) {
+            gem.element = to;
+        }
+    }
 }
 
 fn track_matches(mut events: EventReader<Match>, mut matches: ResMut<Matches>) {
@@ -448,47 +846,77 @@ fn animate_attack(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn player_attack(
-    mut enemies: Query<(&mut Enemy, &mut EnemyAnimator, &EnemyAnimations)>,
+    mut enemies: Query<(Entity, &mut Enemy, &mut EnemyAnimator, &EnemyAnimations)>,
     mut animation_players: Query<&mut AnimationPlayer>,
     matches: Res<Matches>,
+    field: Res<Field>,
     mut player: ResMut<Player>,
+    mut damage_events: EventWriter<DamageDealt>,
+    mut heal_events: EventWriter<Healed>,
+    mut shield_events: EventWriter<Shielded>,
+    mut commands: Commands,
 ) {
     let spell = player.active_spell.as_ref().unwrap();
-    let matches: Vec<_> = matches.0.iter().collect();
-    let damage = matches
-        .iter()
-        .filter(|x| spell.elements.contains(&x.element))
-        .map(|x| x.tiles.len() as u32)
-        .sum::<u32>()
-        * spell.attack;
+    let matches: Vec<MatchedGems> = matches.0.iter().map(MatchedGems::from).collect();
+
+    for (entity, mut enemy, mut animator, animations) in &mut enemies {
+        let breakdown = enemy.state.take_spell(spell, &matches, &field);
+
+        if breakdown.total() != 0 {
+            damage_events.send_batch(breakdown.hits.iter().map(|hit| DamageDealt {
+                target: CombatTarget::Enemy(entity),
+                amount: hit.amount,
+                element: Some(hit.element),
+                combo: hit.matches,
+                critical: hit.critical,
+                absorbed: 0,
+            }));
+
+            animator.play(
+                entity,
+                EnemyAnimation::Hurt,
+                animations,
+                &mut animation_players,
+                &mut commands,
+            );
+        }
+    }
 
-    if damage != 0 {
-        for (mut enemy, mut animator, animations) in &mut enemies {
-            enemy.current_health = enemy.current_health.saturating_sub(damage);
+    let heal = combat::spell_heal(spell, &matches, &player.statuses);
+    let shield = combat::spell_shield(spell, &matches);
 
-            let mut animation_player = animation_players
-                .get_mut(animator.animation_player)
-                .unwrap();
+    let (healed, overhealed) = player.heal(heal.amount);
+    if healed != 0 {
+        heal_events.send(Healed {
+            target: CombatTarget::Player,
+            amount: healed,
+            combo: heal.matches,
+            critical: heal.critical,
+        });
+    }
 
-            animation_player.play(animations.hurt.clone());
-            animator.current_animation = Some(animations.hurt.clone());
-        }
+    for status in heal.applied_statuses {
+        player.statuses.apply(status);
     }
 
-    let heal: u32 = matches
-        .iter()
-        .filter(|x| x.element == Element::Heal)
-        .map(|x| x.tiles.len() as u32)
-        .sum();
+    player.shield += shield.amount;
 
-    player.current_health = player.max_health.min(player.current_health + heal * 3);
+    let shielded = shield.amount + overhealed;
+    if shielded != 0 {
+        shield_events.send(Shielded {
+            target: CombatTarget::Player,
+            amount: shielded,
+            combo: shield.matches,
+        });
+    }
 }
 
 fn end_player_turn(mut commands: Commands, enemies: Query<(&EnemyAnimator, &EnemyAnimations)>) {
-    let enemy_animations_finished = enemies.iter().all(|(animator, animations)| {
-        animator.current_animation.as_ref() == Some(&animations.idle)
-    });
+    let enemy_animations_finished = enemies
+        .iter()
+        .all(|(animator, animations)| animator.is_idle(animations));
 
     if enemy_animations_finished {
         commands.insert_resource(NextState(BattleState::EnemyTurn));
@@ -496,25 +924,25 @@ fn end_player_turn(mut commands: Commands, enemies: Query<(&EnemyAnimator, &Enem
 }
 
 fn kill_enemies(
-    enemies: Query<(Entity, &Enemy, &EnemyAnimations, &EnemyAnimator)>,
+    mut enemies: Query<(Entity, &Enemy, &EnemyAnimations, &mut EnemyAnimator)>,
     mut animation_players: Query<&mut AnimationPlayer>,
     animations: Res<Assets<AnimationClip>>,
     mut commands: Commands,
+    mut kill_events: EventWriter<EnemyKilled>,
 ) {
-    for (entity, enemy, enemy_animations, animator) in &enemies {
-        if enemy.current_health == 0 {
-            let mut animation_player = animation_players
-                .get_mut(animator.animation_player)
-                .unwrap();
-
-            let kill_time = if let Some(animation) = &enemy_animations.death {
-                animation_player.play(animation.clone());
-                animations.get(animation).unwrap().duration()
-            } else {
-                animation_player.pause();
+    for (entity, enemy, enemy_animations, mut animator) in &mut enemies {
+        if enemy.state.current_health == 0 {
+            animator.play(
+                entity,
+                EnemyAnimation::Death,
+                enemy_animations,
+                &mut animation_players,
+                &mut commands,
+            );
 
-                0.0
-            };
+            let kill_time = animator.duration(&animations);
+
+            kill_events.send(EnemyKilled { enemy: entity });
 
             // prevents enemy from returning to idle at the end of the animation
             commands
@@ -542,42 +970,109 @@ fn update_enemy_health_bar(
     for enemy in &enemies {
         let mut progress_bar = progress_bars.get_mut(enemy.health_bar).unwrap();
 
-        progress_bar.percentage = enemy.current_health as f32 / enemy.max_health as f32;
+        progress_bar.percentage = enemy.state.current_health as f32 / enemy.state.max_health as f32;
+    }
+}
+
+#[derive(Component)]
+struct BossHealthBar;
+
+#[derive(Component)]
+struct BossTitle;
+
+fn update_boss_ui(
+    enemies: Query<&Enemy, Changed<Enemy>>,
+    mut bars: Query<&mut ProgressBar, With<BossHealthBar>>,
+    mut titles: Query<&mut Text, With<BossTitle>>,
+) {
+    for enemy in &enemies {
+        let state = &enemy.state;
+
+        if let Some(phase) = state.boss_phase() {
+            for mut bar in &mut bars {
+                bar.percentage = state.current_health as f32 / state.max_health as f32;
+            }
+
+            for mut title in &mut titles {
+                title.sections[0].value = format!("{} - {}", state.definition.name, phase.name);
+            }
+        }
     }
 }
 
 fn update_player_health_bar(
-    mut progress_bars: Query<&mut ProgressBar, With<PlayerHealthBar>>,
+    mut health_bars: Query<&mut ProgressBar, (With<PlayerHealthBar>, Without<PlayerShieldBar>)>,
+    mut shield_bars: Query<(&mut ProgressBar, &mut Transform), With<PlayerShieldBar>>,
     player: Res<Player>,
 ) {
-    for mut health_bar in &mut progress_bars {
+    let health = player.current_health as f32 / player.max_health as f32;
+    let shield = (player.shield as f32 / player.max_health as f32).min(1.0);
+
+    for mut health_bar in &mut health_bars {
         if player.is_changed() || health_bar.is_added() {
-            health_bar.percentage = player.current_health as f32 / player.max_health as f32;
+            health_bar.percentage = health;
+        }
+    }
+
+    for (mut shield_bar, mut transform) in &mut shield_bars {
+        if player.is_changed() || shield_bar.is_added() {
+            shield_bar.percentage = shield;
+            // shields that don't fit after the health overlap the end of it instead
+            transform.translation.x =
+                health.min(1.0 - shield) * (PLAYER_HEALTH_BAR_SIZE.x - PLAYER_HEALTH_BAR_BORDER);
         }
     }
 }
 
 fn enemies_attack(
-    mut enemies: Query<(&Enemy, &mut EnemyAnimator, &EnemyAnimations)>,
+    mut enemies: Query<(Entity, &mut Enemy, &mut EnemyAnimator, &EnemyAnimations)>,
     mut animation_players: Query<&mut AnimationPlayer>,
     mut player: ResMut<Player>,
+    mut damage_events: EventWriter<DamageDealt>,
+    mut commands: Commands,
 ) {
-    for (enemy, mut animator, animations) in &mut enemies {
-        player.current_health = player.current_health.saturating_sub(enemy.attack);
+    let mut new_statuses = vec![];
+
+    for (entity, mut enemy, mut animator, animations) in &mut enemies {
+        let turn = enemy.state.take_turn(&player.statuses);
+
+        new_statuses.extend(turn.applied_statuses);
 
-        let mut animation_player = animation_players
-            .get_mut(animator.animation_player)
-            .unwrap();
+        let damage = match turn.damage {
+            Some(damage) => damage,
+            None => continue,
+        };
+
+        let unblocked = player.take_damage(damage);
+
+        damage_events.send(DamageDealt {
+            target: CombatTarget::Player,
+            amount: unblocked,
+            element: enemy.state.definition.affinity,
+            combo: 1,
+            critical: false,
+            absorbed: damage - unblocked,
+        });
+
+        animator.play(
+            entity,
+            EnemyAnimation::Attack,
+            animations,
+            &mut animation_players,
+            &mut commands,
+        );
+    }
 
-        animation_player.play(animations.attack.clone());
-        animator.current_animation = Some(animations.attack.clone());
+    player.statuses.tick();
+    for status in new_statuses {
+        player.statuses.apply(status);
     }
 }
 
 fn end_enemy_turn(mut commands: Commands, enemies: Query<(&EnemyAnimator, &EnemyAnimations)>) {
-    let enemy_animations_finished = enemies.iter().all(|(animator, animations)| {
-        animator.current_animation.as_ref() == Some(&animations.idle)
-    });
+    let enemy_animations_finished = enemies
+        .iter()
+        .all(|(animator, animations)| animator.is_idle(animations));
 
     if enemy_animations_finished {
         if enemies.iter().count() == 0 {
@@ -619,28 +1114,77 @@ fn fade_out(
 }
 
 #[derive(Component)]
-struct PlayerHealthBar;
+pub struct PlayerHealthBar;
+
+/// Drawn on top of the [`PlayerHealthBar`], right after the player's remaining health.
+#[derive(Component)]
+pub struct PlayerShieldBar;
 
 #[derive(Component)]
-struct BattleCamera;
+pub struct BattleCamera;
 
 pub struct BattlePrefab {
     pub round: u32,
     pub num_rounds: u32,
     pub enemy: EnemyPrefab,
-    pub environment: Handle<Scene>,
+    pub environment: EnvironmentLayout,
+    pub field: Field,
     pub spells: Vec<Spell>,
     pub font: Handle<Font>,
 }
 
 const ENVIRONMENT_LAYER: RenderLayers = RenderLayers::layer(0);
 const BOARD_LAYER: RenderLayers = RenderLayers::layer(1);
-const CARDS_LAYER: RenderLayers = RenderLayers::layer(2);
+pub const CARDS_LAYER: RenderLayers = RenderLayers::layer(2);
+
+const BOSS_BAR_SIZE: Vec2 = Vec2::new(5.0, 0.25);
 
 impl Prefab for BattlePrefab {
     fn construct(self, entity: &mut EntityCommands) {
         let mut root_entities = vec![entity.id()];
 
+        let boss = self.enemy.definition.boss.clone();
+
+        if let Some(boss) = &boss {
+            root_entities.push(
+                entity
+                    .commands()
+                    .spawn_prefab(ProgressBarPrefab {
+                        starting_percentage: 1.0,
+                        size: BOSS_BAR_SIZE,
+                        border: 0.05,
+                        transform: Transform::from_xyz(0.0, 2.2, 0.0),
+                        color: Color::hex(HEALTH_COLOR_HEX).unwrap(),
+                        ..default()
+                    })
+                    .insert(BOARD_LAYER)
+                    .insert(BossHealthBar)
+                    .with_children(|p| {
+                        // marks where the next phases start
+                        for phase in boss.phases.iter().skip(1) {
+                            p.spawn_bundle(PbrBundle {
+                                mesh: square_mesh(),
+                                material: white_standard_material(),
+                                transform: Transform::from_xyz(
+                                    BOSS_BAR_SIZE.x * (phase.threshold - 0.5),
+                                    0.0,
+                                    0.01,
+                                )
+                                .with_scale(Vec3::new(
+                                    0.04,
+                                    BOSS_BAR_SIZE.y + 0.1,
+                                    1.0,
+                                )),
+                                ..default()
+                            })
+                            .insert(NotShadowCaster)
+                            .insert(NotShadowReceiver);
+                        }
+                    })
+                    .id(),
+            );
+        }
+
         root_entities.push(
             entity
                 .commands()
@@ -675,19 +1219,32 @@ impl Prefab for BattlePrefab {
                 .commands()
                 .spawn_prefab(BoardPrefab {
                     layers: BOARD_LAYER,
-                    gems: BoardPrefab::random_gems(),
+                    gems: BoardPrefab::random_gems(&self.field),
                     transform: Transform::from_xyz(0.0, -0.5, 0.0).with_scale(Vec3::splat(0.5)),
                 })
                 .with_children(|p| {
                     p.spawn_prefab(ProgressBarPrefab {
                         starting_percentage: 1.0,
-                        size: [6.0, 0.3].into(),
-                        border: 0.1,
+                        size: PLAYER_HEALTH_BAR_SIZE,
+                        border: PLAYER_HEALTH_BAR_BORDER,
                         transform: Transform::from_xyz(0.0, -2.9, 1.0),
                         color: Color::hex(HEALTH_COLOR_HEX).unwrap(),
                         ..default()
                     })
-                    .insert(PlayerHealthBar);
+                    .insert(PlayerHealthBar)
+                    .with_children(|p| {
+                        p.spawn_prefab(ProgressBarPrefab {
+                            starting_percentage: 0.0,
+                            size: PLAYER_HEALTH_BAR_SIZE,
+                            border: PLAYER_HEALTH_BAR_BORDER,
+                            transform: Transform::from_xyz(0.0, 0.0, 0.01),
+                            color: SHIELD_COLOR,
+                            border_color: Color::NONE,
+                            background_color: Color::NONE,
+                            ..default()
+                        })
+                        .insert(PlayerShieldBar);
+                    });
                 })
                 .id(),
         );
@@ -738,13 +1295,30 @@ impl Prefab for BattlePrefab {
                         transform: Transform::from_xyz(-1500.0, 2000.0, 0.0),
                         ..default()
                     });
+
+                    if let Some(boss) = &boss {
+                        c.spawn_bundle(Text2dBundle {
+                            text: Text::from_section(
+                                format!("{} - {}", self.enemy.definition.name, boss.phases[0].name),
+                                TextStyle {
+                                    font: self.font.clone(),
+                                    font_size: 150.0,
+                                    color: Color::WHITE,
+                                },
+                            )
+                            .with_alignment(TextAlignment::CENTER),
+                            transform: Transform::from_xyz(0.0, 1700.0, 0.0),
+                            ..default()
+                        })
+                        .insert(BossTitle);
+                    }
                 })
                 .id(),
         );
 
         entity
             .insert_bundle(SceneBundle {
-                scene: self.environment.clone(),
+                scene: self.environment.scene.clone(),
                 ..default()
             })
             .insert(ENVIRONMENT_LAYER)
@@ -754,8 +1328,7 @@ impl Prefab for BattlePrefab {
                         is_active: false,
                         ..default()
                     },
-                    transform: Transform::from_xyz(0.0, 5.0, 13.0)
-                        .looking_at([0.0, 0.0, 5.0].into(), Vec3::Y),
+                    transform: self.environment.camera,
                     ..default()
                 })
                 .insert(BattleCamera)
@@ -764,17 +1337,8 @@ impl Prefab for BattlePrefab {
                 p.spawn_prefab(self.enemy);
 
                 p.spawn_bundle(PointLightBundle {
-                    point_light: PointLight {
-                        shadows_enabled: true,
-                        range: 50.0,
-                        intensity: 100000.0,
-                        shadow_depth_bias: 0.001,
-                        ..default()
-                    },
-                    transform: Transform::from_xyz(10.0, 10.0, 10.0).with_rotation(
-                        Quat::from_rotation_x(-45_f32.to_radians())
-                            * Quat::from_rotation_y(-45_f32.to_radians()),
-                    ),
+                    point_light: self.environment.point_light.clone(),
+                    transform: self.environment.light,
                     ..default()
                 })
                 .insert(BattleCamera);
@@ -783,18 +1347,23 @@ impl Prefab for BattlePrefab {
         entity
             .commands()
             .insert_resource(BattleResources { root_entities });
+
+        entity.commands().insert_resource(self.field);
     }
 }
 
 #[derive(Clone)]
 pub struct EnemyPrefab {
     pub transform: Transform,
-    pub kind: EnemyKind,
+    pub definition: EnemyDefinition,
     pub max_health: u32,
     pub attack: u32,
 }
 
 const HEALTH_COLOR_HEX: &str = "871e16";
+pub const SHIELD_COLOR: Color = Color::rgba(0.6, 0.85, 1.0, 0.8);
+const PLAYER_HEALTH_BAR_SIZE: Vec2 = Vec2::new(6.0, 0.3);
+const PLAYER_HEALTH_BAR_BORDER: f32 = 0.1;
 
 impl Prefab for EnemyPrefab {
     fn construct(self, entity: &mut EntityCommands) {
@@ -803,24 +1372,28 @@ impl Prefab for EnemyPrefab {
                 starting_percentage: 1.0,
                 border: 0.1,
                 size: [1.0, 0.2].into(),
-                transform: self.transform * Transform::from_xyz(0.0, 0.2, 1.2),
+                transform: Transform::from_xyz(0.0, 0.2, 1.2),
                 color: Color::hex(HEALTH_COLOR_HEX).unwrap(),
                 ..default()
             })
             .id()
         });
 
+        let scale = self
+            .definition
+            .boss
+            .as_ref()
+            .map(|x| x.scale)
+            .unwrap_or(1.0);
+
         entity
             .insert_bundle(SceneBundle {
-                scene: self.kind.scene_handle(),
-                transform: self.transform,
+                scene: self.definition.scene_handle(),
+                transform: self.transform * Transform::from_scale(Vec3::splat(scale)),
                 ..default()
             })
             .insert(Enemy {
-                kind: self.kind,
-                max_health: self.max_health,
-                current_health: self.max_health,
-                attack: self.attack,
+                state: EnemyState::new(self.definition, self.max_health, self.attack),
                 health_bar,
             });
     }
@@ -828,62 +1401,12 @@ impl Prefab for EnemyPrefab {
 
 #[derive(Component)]
 pub struct Enemy {
-    kind: EnemyKind,
-    max_health: u32,
-    current_health: u32,
-    attack: u32,
+    state: EnemyState,
     health_bar: Entity,
 }
 
-#[derive(Clone, Copy, EnumVariantNames, EnumIter, EnumCount, Display)]
-pub enum EnemyKind {
-    Alien,
-    Bat,
-    Bee,
-    Cactus,
-    Chicken,
-    Crab,
-    Cthulhu,
-    Cyclops,
-    Deer,
-    Demon,
-    Ghost,
-    GreenDemon,
-    Mushroom,
-    Panda,
-    Penguin,
-    Pig,
-    Skull,
-    Tree,
-    YellowDragon,
-    Yeti,
-}
-
-impl EnemyKind {
-    pub fn random() -> Self {
-        let rng = fastrand::Rng::new();
-
-        let n = rng.usize(..Self::COUNT);
-        Self::iter().nth(n).unwrap()
-    }
-
-    pub fn gltf_paths() -> Vec<String> {
-        Self::iter().map(|x| x.gltf_path()).collect()
-    }
-
-    pub fn scene_handle(&self) -> Handle<Scene> {
-        let path = format!("models/enemies/{self}.glb#Scene0");
-
-        Handle::weak(HandleId::AssetPathId(path.as_str().into()))
-    }
-
-    pub fn gltf_path(&self) -> String {
-        format!("models/enemies/{self}.glb")
-    }
-
-    pub fn gltf_handle(&self) -> Handle<Gltf> {
-        let path = self.gltf_path();
-
-        Handle::weak(HandleId::AssetPathId(path.as_str().into()))
+impl Enemy {
+    pub fn health_bar(&self) -> Entity {
+        self.health_bar
     }
 }
@@ -0,0 +1,49 @@
+//! Plays thousands of seeded runs without rendering and writes the results as CSV.
+//!
+//! `cargo run --release --bin simulate -- --runs 1000 --strategy greedy,solver`
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use bevy_jam_2::sim::{self, SimOptions};
+
+const USAGE: &str = "usage: simulate [options]
+
+options:
+    --runs <n>          runs per strategy (default 1000)
+    --seed <n>          seed of the first run, the others count up from it (default 0)
+    --strategy <names>  comma separated list of random, greedy and solver (default all)
+    --assets <path>     assets folder (default assets)
+    --out <path>        folder the CSV files are written to (default sim_results)";
+
+fn main() -> anyhow::Result<()> {
+    let mut options = SimOptions::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("missing value for {arg}"))
+        };
+
+        match arg.as_str() {
+            "--runs" => options.runs = value()?.parse()?,
+            "--seed" => options.seed = value()?.parse()?,
+            "--strategy" => {
+                options.strategies = value()?
+                    .split(',')
+                    .map(|x| x.trim().parse())
+                    .collect::<Result<_, _>>()?
+            }
+            "--assets" => options.assets = PathBuf::from(value()?),
+            "--out" => options.output = PathBuf::from(value()?),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ => bail!("unknown argument {arg}\n\n{USAGE}"),
+        }
+    }
+
+    sim::run(&options)
+}
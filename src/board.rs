@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use crate::combat::Field;
 use crate::prefab::*;
 use crate::tween_untils::TweenType;
 use crate::utils::{
@@ -20,6 +21,7 @@ use bevy_tweening::{
     Animator, Delay, EaseFunction, Tween, TweenCompleted, TweeningType,
 };
 use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
 use strum::{EnumCount, IntoEnumIterator};
 use strum_macros::{Display, EnumCount, EnumIter, EnumVariantNames};
 
@@ -29,6 +31,7 @@ impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<Match>()
             .add_event::<Fall>()
+            .init_resource::<BoardSettings>()
             .add_startup_system(add_meshes)
             .add_startup_system(add_materials)
             .add_startup_system(load_icons)
@@ -93,6 +96,14 @@ fn add_materials(mut materials: ResMut<Assets<StandardMaterial>>) {
     for element in Element::iter() {
         materials.set_untracked(element.material_handle(), element.material())
     }
+
+    materials.set_untracked(
+        hidden_gem_material_handle(),
+        StandardMaterial {
+            base_color: HIDDEN_GEM_COLOR * 0.6,
+            ..Element::Heal.material()
+        },
+    );
 }
 
 fn load_icons(asset_server: Res<AssetServer>, mut loading: ResMut<Loading>) {
@@ -110,16 +121,27 @@ fn change_gem_material(
     gems: Query<&Gem>,
     mut meshes: Query<&mut Handle<StandardMaterial>>,
     state: Res<CurrentState<BoardState>>,
+    field: Res<Field>,
 ) {
     for (tile, hover) in &tiles {
         if let Ok(gem) = gems.get(tile.gem) {
             if let Ok(mut material) = meshes.get_mut(gem.mesh) {
+                let hidden = field.is_hidden(gem.element);
+
                 *material = if (state.0 == BoardState::Ready && hover.is_cursor_in) || gem.holding {
+                    let color = if hidden {
+                        HIDDEN_GEM_COLOR
+                    } else {
+                        gem.element.color()
+                    };
+
                     materials.add(StandardMaterial {
-                        base_color: gem.element.color(),
-                        emissive: gem.element.color() * 0.5,
+                        base_color: color,
+                        emissive: color * 0.5,
                         ..gem.element.material()
                     })
+                } else if hidden {
+                    hidden_gem_material_handle()
                 } else {
                     gem.element.material_handle()
                 };
@@ -128,6 +150,23 @@ fn change_gem_material(
     }
 }
 
+pub const DEFAULT_MOVE_TIME: f32 = 9.0;
+/// Hazards can not take the move time below this.
+pub const MIN_MOVE_TIME: f32 = 2.0;
+
+pub struct BoardSettings {
+    /// Seconds the player can move a gem for after the first swap.
+    pub move_time: f32,
+}
+
+impl Default for BoardSettings {
+    fn default() -> Self {
+        Self {
+            move_time: DEFAULT_MOVE_TIME,
+        }
+    }
+}
+
 struct Swapping {
     swaps: u32,
     gem: Entity,
@@ -146,6 +185,7 @@ fn pickup_gem(
     mut events: EventReader<MouseButtonInput>,
     tiles: Query<(Entity, &Tile, &WorldHover)>,
     mut gems: Query<&mut Gem>,
+    settings: Res<BoardSettings>,
     mut commands: Commands,
 ) {
     let start_pickup = events
@@ -160,7 +200,7 @@ fn pickup_gem(
                     swaps: 0,
                     gem: tile.gem,
                     current_tile: entity,
-                    timer: Timer::from_seconds(9.0, false),
+                    timer: Timer::from_seconds(settings.move_time, false),
                     world_cursor: hover.cursors_in_bounds[0],
                 });
                 commands.insert_resource(NextState(BoardState::Swapping));
@@ -289,68 +329,77 @@ pub struct Match {
     pub element: Element,
 }
 
-#[derive(Clone, Copy)]
-struct TileInfo {
-    tile: Entity,
-    element: Element,
-}
-
 fn match_gems(
     boards: Query<&Board>,
     tiles: Query<&Tile>,
     gems: Query<&Gem>,
     mut events: EventWriter<Match>,
 ) {
-    // todo: combine adjacent matches
-
     let board = boards.single();
 
-    let mut rows = vec![Vec::new(); 5];
-    let mut columns = vec![Vec::new(); 6];
-
+    let mut elements = [[None; 5]; 6];
     for (x, column) in board.tiles.iter().enumerate() {
         for (y, &entity) in column.iter().enumerate() {
             let tile = tiles.get(entity).unwrap();
             let gem = gems.get(tile.gem).unwrap();
-            let info = TileInfo {
-                tile: entity,
-                element: gem.element,
-            };
 
-            columns[x].push(info);
-            rows[y].push(info);
+            elements[x][y] = Some(gem.element);
         }
     }
 
+    let matches = find_matches(&elements).into_iter().map(|gem_match| Match {
+        tiles: gem_match
+            .positions
+            .iter()
+            .map(|&(x, y)| board.tiles[x][y])
+            .collect(),
+        element: gem_match.element,
+    });
+
+    events.send_batch(matches);
+}
+
+/// A match found on a grid of gems, indexed by column then row.
+#[derive(Debug, Clone)]
+pub struct GemMatch {
+    pub positions: HashSet<(usize, usize)>,
+    pub element: Element,
+}
+
+/// Finds every line of 3 or more gems of the same element. Lines sharing a gem are merged
+/// into a single match and empty spaces never match.
+pub fn find_matches(gems: &[[Option<Element>; 5]; 6]) -> Vec<GemMatch> {
+    // todo: combine adjacent matches
+
+    let rows = (0..5).map(|y| (0..6).map(|x| (x, y)).collect::<Vec<_>>());
+    let columns = (0..6).map(|x| (0..5).map(|y| (x, y)).collect::<Vec<_>>());
+
     let mut matches = Vec::new();
-    for row in rows.iter().chain(&columns) {
-        let mut row = row.iter();
-        let first = row.next().unwrap();
-        let mut current_match = Match {
-            tiles: [first.tile].into_iter().collect(),
-            element: first.element,
-        };
+    for line in rows.chain(columns) {
+        let mut current: Option<GemMatch> = None;
 
-        for info in row {
-            if current_match.element == info.element {
-                current_match.tiles.insert(info.tile);
-            } else {
-                let previous = std::mem::replace(
-                    &mut current_match,
-                    Match {
-                        tiles: [info.tile].into_iter().collect(),
-                        element: info.element,
-                    },
-                );
+        for (x, y) in line {
+            let element = gems[x][y];
 
-                if previous.tiles.len() >= 3 {
-                    matches.push(previous);
-                };
+            if let (Some(current), Some(element)) = (&mut current, element) {
+                if current.element == element {
+                    current.positions.insert((x, y));
+                    continue;
+                }
             }
+
+            if let Some(previous) = current.take().filter(|x| x.positions.len() >= 3) {
+                matches.push(previous);
+            }
+
+            current = element.map(|element| GemMatch {
+                positions: [(x, y)].into_iter().collect(),
+                element,
+            });
         }
 
-        if current_match.tiles.len() >= 3 {
-            matches.push(current_match);
+        if let Some(current) = current.filter(|x| x.positions.len() >= 3) {
+            matches.push(current);
         }
     }
 
@@ -359,9 +408,9 @@ fn match_gems(
         let mut current = matches.remove(index);
         let mut i = 0;
         while i < matches.len() {
-            if !matches[i].tiles.is_disjoint(&current.tiles) {
+            if !matches[i].positions.is_disjoint(&current.positions) {
                 let linked = matches.remove(i);
-                current.tiles.extend(linked.tiles);
+                current.positions.extend(linked.positions);
             } else {
                 i += 1;
             }
@@ -371,7 +420,7 @@ fn match_gems(
         index += 1;
     }
 
-    events.send_batch(matches.into_iter());
+    matches
 }
 
 pub const MATCH_START_DELAY: f32 = 0.1;
@@ -513,8 +562,11 @@ fn move_falling_gems(
     mut fall_events: EventReader<Fall>,
     mut tiles: Query<(&mut Tile, &Transform, &Parent)>,
     transforms: Query<&Transform>,
+    field: Res<Field>,
     mut commands: Commands,
 ) {
+    let rng = fastrand::Rng::new();
+
     for event in fall_events.iter() {
         let (mut tile, &transform, board) = tiles.get_mut(event.tile).unwrap();
 
@@ -529,7 +581,7 @@ fn move_falling_gems(
                 (
                     commands
                         .spawn_prefab(GemPrefab {
-                            element: Element::random(),
+                            element: field.skyfall(&rng),
                             transform: Transform::from_translation(translation),
                         })
                         .id(),
@@ -577,7 +629,20 @@ fn stop_falling(
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumVariantNames, EnumIter, EnumCount, Display)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    EnumVariantNames,
+    EnumIter,
+    EnumCount,
+    Display,
+    Deserialize,
+    Serialize,
+)]
 pub enum Element {
     Heal,
     Dark,
@@ -588,9 +653,7 @@ pub enum Element {
 }
 
 impl Element {
-    fn random() -> Element {
-        let rng = fastrand::Rng::new();
-
+    pub fn random_with(rng: &fastrand::Rng) -> Element {
         let n = rng.usize(..Self::COUNT);
         Self::iter().nth(n).unwrap()
     }
@@ -637,6 +700,14 @@ impl Element {
 }
 
 const GEM_MESH_ID: HandleId = HandleId::new(Mesh::TYPE_UUID, 10_000);
+const HIDDEN_GEM_MATERIAL_ID: HandleId =
+    HandleId::new(StandardMaterial::TYPE_UUID, 10_000 + Element::COUNT as u64);
+/// Gems hidden by a field effect are drawn in this color instead of their element's.
+const HIDDEN_GEM_COLOR: Color = Color::SILVER;
+
+fn hidden_gem_material_handle() -> Handle<StandardMaterial> {
+    Handle::weak(HIDDEN_GEM_MATERIAL_ID)
+}
 
 #[derive(Component)]
 pub struct Gem {
@@ -701,11 +772,13 @@ pub struct BoardPrefab {
 }
 
 impl BoardPrefab {
-    pub fn random_gems() -> [[Element; 5]; 6] {
+    pub fn random_gems(field: &Field) -> [[Element; 5]; 6] {
+        let rng = fastrand::Rng::new();
+
         let mut gems = [[Element::Heal; 5]; 6];
         for column in &mut gems {
             for gem in column {
-                *gem = Element::random();
+                *gem = field.skyfall(&rng);
             }
         }
 
@@ -1,14 +1,21 @@
+use std::time::Duration;
+
 use bevy::{
+    ecs::system::AsSystemLabel,
     input::{mouse::MouseButtonInput, ButtonState},
     prelude::*,
     render::view::RenderLayers,
-    utils::HashSet,
+    text::Text2dBounds,
 };
+use bevy_tweening::{Animator, Delay, EaseFunction, Lens, Tween, TweeningType};
 use iyes_loopless::prelude::*;
 
 use crate::{
-    player::{Player, Spell},
+    board::Element,
+    main_state::Difficulty,
+    player::{Evolution, Player, Spell, MAX_MERGE},
     prefab::*,
+    recipes::{RecipeBook, Recipes},
     utils::{blue_color_material, go_to, square_mesh, white_color_material, WorldHover},
 };
 
@@ -20,19 +27,26 @@ impl Plugin for CardPlugin {
             .add_system(put_cards_in_pile)
             .add_system(put_cards_in_hand)
             .add_system(hover_active_card.run_not_in_state(CardsState::None))
+            .add_system(show_card_actions)
+            .add_system(update_energy_text)
+            .add_system(show_card_tooltip)
             .add_enter_system(CardsState::Draw, draw)
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(CardsState::Draw)
-                    .with_system(go_to(CardsState::Select))
+                    .after(put_cards_in_hand.as_system_label())
+                    .with_system(wait_for_cards(CardsState::Select))
                     .into(),
             )
+            .add_system(update_pile_counts)
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(CardsState::Select)
-                    .with_system(hover_cards.chain(select_cards).chain(start_merge))
+                    .run_if(pile_viewer_closed)
+                    .with_system(hover_cards.chain(select_cards).chain(press_card_actions))
                     .into(),
             )
+            .add_system(view_piles.run_in_state(CardsState::Select))
             .add_enter_system(CardsState::Merge, merge)
             .add_system_set(
                 ConditionSet::new()
@@ -44,7 +58,8 @@ impl Plugin for CardPlugin {
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(CardsState::Discard)
-                    .with_system(go_to(CardsState::End))
+                    .after(put_cards_in_pile.as_system_label())
+                    .with_system(wait_for_cards(CardsState::End))
                     .into(),
             );
     }
@@ -60,16 +75,93 @@ pub enum CardsState {
     End,
 }
 
+const CARD_MOVE_TIME: f32 = 0.3;
+const CARD_HOVER_TIME: f32 = 0.1;
+/// Time between cards leaving for or coming from the hand.
+const CARD_STAGGER: f32 = 0.08;
+
+/// Where a card is headed and when it gets there.
+#[derive(Component, Default)]
+struct CardTarget {
+    transform: Transform,
+    in_hand: bool,
+    arrival: f64,
+}
+
+struct CardMove {
+    end: Transform,
+    in_hand: bool,
+    delay: f32,
+    seconds: f32,
+}
+
+impl CardTarget {
+    /// Tweens the card from where it is now, unless it's already headed there.
+    fn move_to(
+        &mut self,
+        card: CardMove,
+        (entity, from): (Entity, &Transform),
+        time: &Time,
+        commands: &mut Commands,
+    ) {
+        if self.transform == card.end && self.in_hand == card.in_hand {
+            return;
+        }
+
+        let tween = Tween::new(
+            EaseFunction::QuadraticInOut,
+            TweeningType::Once,
+            Duration::from_secs_f32(card.seconds),
+            CardLens {
+                start: *from,
+                end: card.end,
+            },
+        );
+
+        if card.delay > 0.0 {
+            let delay = Delay::new(Duration::from_secs_f32(card.delay));
+            commands
+                .entity(entity)
+                .insert(Animator::new(delay.then(tween)));
+        } else {
+            commands.entity(entity).insert(Animator::new(tween));
+        }
+
+        *self = CardTarget {
+            transform: card.end,
+            in_hand: card.in_hand,
+            arrival: time.seconds_since_startup() + (card.delay + card.seconds) as f64,
+        };
+    }
+}
+
+/// Moves, turns and scales a card at once, so cards flip over on their way out of the piles.
+struct CardLens {
+    start: Transform,
+    end: Transform,
+}
+
+impl Lens<Transform> for CardLens {
+    fn lerp(&mut self, target: &mut Transform, ratio: f32) {
+        target.translation = self.start.translation.lerp(self.end.translation, ratio);
+        target.rotation = self.start.rotation.slerp(self.end.rotation, ratio);
+        target.scale = self.start.scale.lerp(self.end.scale, ratio);
+    }
+}
+
 #[allow(clippy::type_complexity)]
 fn put_cards_in_hand(
     hands: Query<(&Hand, &Transform, &WorldHover), (Changed<Hand>, Changed<WorldHover>)>,
-    mut cards: Query<&mut Transform, Without<Hand>>,
+    mut cards: Query<(Entity, &Transform, &mut CardTarget)>,
+    time: Res<Time>,
+    mut commands: Commands,
 ) {
-    let space = 500.0;
     let hover_offset = Vec3::new(0.0, 100.0, 10.0);
     let selected_offset = Vec3::new(0.0, 200.0, 0.0);
 
     for (hand, hand_transform, hover) in &hands {
+        // bigger hands squeeze together to stay clear of the piles
+        let space = 500_f32.min(2500.0 / hand.cards.len().max(1) as f32);
         let offset = (hand.cards.len() / 2) as f32 * space;
         let mut iter = cards.iter_many_mut(&hand.cards);
 
@@ -81,51 +173,122 @@ fn put_cards_in_hand(
             };
 
         let mut i = 0.0;
-        while let Some(mut transform) = iter.fetch_next() {
-            *transform = root * Transform::from_xyz(i * space - offset, 0.0, i + 10.0);
-            i += 1.0;
-        }
+        let mut delay = 0.0;
+        while let Some((entity, transform, mut target)) = iter.fetch_next() {
+            let mut end = root * Transform::from_xyz(i * space - offset, 0.0, i + 10.0);
+            if hand.hovered_card == Some(entity) {
+                end.translation += hover_offset;
+            }
+            if hand.selected_cards.contains(&entity) {
+                end.translation += selected_offset;
+            }
 
-        if let Some(mut transform) = hand.hovered_card.and_then(|x| cards.get_mut(x).ok()) {
-            transform.translation += hover_offset;
-        }
+            // drawn cards fan in one after another, cards already in the hand just shift
+            let drawn = !target.in_hand;
+            let card = CardMove {
+                end,
+                in_hand: true,
+                delay: if drawn { delay } else { 0.0 },
+                seconds: if drawn {
+                    CARD_MOVE_TIME
+                } else {
+                    CARD_HOVER_TIME
+                },
+            };
+            target.move_to(card, (entity, transform), &time, &mut commands);
 
-        for entity in &hand.selected_cards {
-            if let Ok(mut transform) = cards.get_mut(*entity) {
-                transform.translation += selected_offset;
+            if drawn {
+                delay += CARD_STAGGER;
             }
+            i += 1.0;
         }
     }
 }
 
 fn put_cards_in_pile(
     piles: Query<(&Pile, &Transform), Changed<Pile>>,
-    mut cards: Query<&mut Transform, Without<Pile>>,
+    mut cards: Query<(Entity, &Transform, &mut CardTarget)>,
+    time: Res<Time>,
+    mut commands: Commands,
 ) {
     for (pile, pile_transform) in &piles {
+        let end = pile_transform.with_rotation(Quat::from_rotation_y(180_f32.to_radians()));
+
+        let mut delay = 0.0;
         let mut iter = cards.iter_many_mut(&pile.cards);
-        while let Some(mut transform) = iter.fetch_next() {
-            *transform = pile_transform.with_rotation(Quat::from_rotation_y(180_f32.to_radians()));
+        while let Some((entity, transform, mut target)) = iter.fetch_next() {
+            let discarded = target.in_hand;
+            let card = CardMove {
+                end,
+                in_hand: false,
+                delay: if discarded { delay } else { 0.0 },
+                seconds: CARD_MOVE_TIME,
+            };
+            target.move_to(card, (entity, transform), &time, &mut commands);
+
+            if discarded {
+                delay += CARD_STAGGER;
+            }
+        }
+    }
+}
+
+/// Goes to the next state once every card got where it was headed.
+fn wait_for_cards(next: CardsState) -> impl Fn(Res<Time>, Query<&CardTarget>, Commands) {
+    move |time, cards, mut commands| {
+        let now = time.seconds_since_startup();
+
+        if cards.iter().all(|x| x.arrival <= now) {
+            commands.insert_resource(NextState(next));
+        }
+    }
+}
+
+/// Draws up to `count` cards from the top of the draw pile, shuffling the discard pile back in
+/// whenever it runs out. Draws fewer when both piles are empty.
+pub fn draw_cards<T>(
+    draw_pile: &mut Vec<T>,
+    discard_pile: &mut Vec<T>,
+    count: usize,
+    mut shuffle: impl FnMut(&mut [T]),
+) -> Vec<T> {
+    let mut drawn = Vec::with_capacity(count);
+
+    while drawn.len() < count {
+        if draw_pile.is_empty() {
+            if discard_pile.is_empty() {
+                break;
+            }
+
+            shuffle(discard_pile.as_mut_slice());
+            draw_pile.append(discard_pile);
         }
+
+        drawn.push(draw_pile.remove(0));
     }
+
+    drawn
 }
 
 fn draw(
     mut draw_piles: Query<&mut Pile, With<DrawPile>>,
     mut hands: Query<(Entity, &mut Hand)>,
     mut discard_piles: Query<&mut Pile, (With<DiscardPile>, Without<DrawPile>)>,
+    mut player: ResMut<Player>,
+    difficulty: Res<Difficulty>,
     mut commands: Commands,
 ) {
     let mut draw_pile = draw_piles.single_mut();
     let (entity, mut hand) = hands.single_mut();
     let mut discard_pile = discard_piles.single_mut();
 
-    if draw_pile.cards.len() < 5 {
-        fastrand::shuffle(&mut discard_pile.cards);
-        draw_pile.cards.append(&mut discard_pile.cards);
-    }
-
-    hand.cards.extend(draw_pile.cards.drain(..5));
+    let cards = draw_cards(
+        &mut draw_pile.cards,
+        &mut discard_pile.cards,
+        player.take_draw_count(difficulty.hand_size_penalty()),
+        fastrand::shuffle,
+    );
+    hand.cards.extend(cards);
 
     commands.entity(entity).despawn_descendants();
 }
@@ -153,63 +316,111 @@ fn hover_cards(mut hands: Query<&mut Hand>, cards: Query<&WorldHover>) {
     }
 }
 
-fn select_cards(mut hands: Query<&mut Hand>, mut events: EventReader<MouseButtonInput>) {
-    let clicked = events
+fn left_clicked(events: &mut EventReader<MouseButtonInput>) -> bool {
+    events
         .iter()
-        .any(|e| e.state == ButtonState::Pressed && e.button == MouseButton::Left);
+        .any(|e| e.state == ButtonState::Pressed && e.button == MouseButton::Left)
+}
 
-    if clicked {
-        for mut hand in &mut hands {
-            if let Some(card) = hand.hovered_card {
-                if !hand.selected_cards.insert(card) {
-                    hand.selected_cards.remove(&card);
+fn select_cards(
+    mut hands: Query<&mut Hand>,
+    spells: Query<&Spell>,
+    player: Res<Player>,
+    mut events: EventReader<MouseButtonInput>,
+) {
+    if !left_clicked(&mut events) {
+        return;
+    }
+
+    for mut hand in &mut hands {
+        if let Some(card) = hand.hovered_card {
+            if let Some(i) = hand.selected_cards.iter().position(|&x| x == card) {
+                hand.selected_cards.remove(i);
+            } else {
+                // cards that would break a merge limit or cost too much can't be added
+                let mut selected: Vec<_> = spells.iter_many(&hand.selected_cards).collect();
+                selected.push(spells.get(card).unwrap());
+
+                let cost: u32 = selected.iter().map(|x| x.cost).sum();
+                if Spell::can_merge(&selected) && cost <= player.energy {
+                    hand.selected_cards.push(card);
                 }
             }
         }
     }
 }
 
-fn start_merge(hands: Query<&Hand>, mut commands: Commands) {
-    for hand in &hands {
-        if hand.selected_cards.len() == 2 {
-            commands.insert_resource(NextState(CardsState::Merge));
-            break;
+fn press_card_actions(
+    mut hands: Query<&mut Hand>,
+    actions: Query<(&CardAction, &WorldHover)>,
+    mut events: EventReader<MouseButtonInput>,
+    mut commands: Commands,
+) {
+    if !left_clicked(&mut events) {
+        return;
+    }
+
+    let action = actions
+        .iter()
+        .find(|(_, hover)| hover.is_cursor_in)
+        .map(|(action, _)| *action);
+
+    for mut hand in &mut hands {
+        match action {
+            Some(CardAction::Cast) if !hand.selected_cards.is_empty() => {
+                commands.insert_resource(NextState(CardsState::Merge));
+            }
+            Some(CardAction::Cancel) => hand.selected_cards.clear(),
+            _ => {}
+        }
+    }
+}
+
+fn show_card_actions(
+    state: Res<CurrentState<CardsState>>,
+    hands: Query<&Hand>,
+    mut actions: Query<&mut Visibility, With<CardAction>>,
+) {
+    let is_visible =
+        state.0 == CardsState::Select && hands.iter().any(|x| !x.selected_cards.is_empty());
+
+    for mut visibility in &mut actions {
+        if visibility.is_visible != is_visible {
+            visibility.is_visible = is_visible;
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn merge(
     mut hands: Query<(Entity, &mut Hand)>,
     mut player: ResMut<Player>,
-    cards: Query<&Spell>,
+    evolution: Res<Evolution>,
+    cards: Query<(&Spell, &DeckCard)>,
+    recipes: Res<Recipes>,
+    books: Res<Assets<RecipeBook>>,
+    // only used when the recipe book failed to load
+    fallback: Local<RecipeBook>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
 ) {
     let (entity, mut hand) = hands.single_mut();
 
-    let mut new_spell = Spell::empty();
-
-    let mut spells = hand.selected_cards.drain();
+    let (spells, deck_cards): (Vec<_>, Vec<_>) = cards
+        .iter_many(hand.selected_cards.drain(..))
+        .map(|(spell, card)| (spell, card.0))
+        .unzip();
 
-    let a = cards.get(spells.next().unwrap()).unwrap();
-    let b = cards.get(spells.next().unwrap()).unwrap();
+    let book = books.get(&recipes.book).unwrap_or(&fallback);
+    let new_spell = book.merge(&spells);
 
-    new_spell.attack = a.attack + b.attack;
-
-    if a.name == b.name {
-        new_spell.name = ("Big ".to_string() + a.name.as_ref()).into();
-    } else {
-        new_spell.name = (a.name_modifier().to_string() + " " + b.name.as_ref()).into();
-    }
-
-    match &mut new_spell.elements {
-        std::borrow::Cow::Borrowed(_) => todo!(),
-        std::borrow::Cow::Owned(vec) => {
-            vec.extend_from_slice(&a.elements);
-            vec.extend_from_slice(&b.elements);
-        }
+    // the deck changes show up from the next battle on
+    if evolution.learn_recipes && spells.len() > 1 && book.is_learned(&new_spell) {
+        player.learn(&new_spell);
     }
+    player.record_merge(&deck_cards, &evolution);
 
+    player.energy = player.energy.saturating_sub(new_spell.cost);
     player.active_spell = Some(new_spell.clone());
     commands.entity(entity).with_children(|p| {
         p.spawn_prefab(CardPrefab {
@@ -220,9 +431,84 @@ fn merge(
     });
 }
 
+/// Index of the card in [`Player::spells`].
+#[derive(Component, Clone, Copy)]
+struct DeckCard(usize);
+
 #[derive(Component)]
 struct ActiveCard;
 
+const ENERGY_COLOR: Color = Color::rgb(0.2, 0.5, 1.0);
+
+/// Shows the rules of the card hovered in the hand.
+#[derive(Component)]
+struct CardTooltip;
+
+#[derive(Component)]
+struct CardTooltipText;
+
+fn show_card_tooltip(
+    state: Res<CurrentState<CardsState>>,
+    hands: Query<&Hand>,
+    spells: Query<&Spell>,
+    mut tooltips: Query<&mut Visibility, With<CardTooltip>>,
+    mut texts: Query<&mut Text, With<CardTooltipText>>,
+    mut shown: Local<Option<Entity>>,
+) {
+    let hovered = hands
+        .iter()
+        .find_map(|x| x.hovered_card)
+        .filter(|_| state.0 == CardsState::Select);
+
+    if *shown == hovered {
+        return;
+    }
+    *shown = hovered;
+
+    for mut visibility in &mut tooltips {
+        visibility.is_visible = hovered.is_some();
+    }
+
+    if let Some(spell) = hovered.and_then(|x| spells.get(x).ok()) {
+        for mut text in &mut texts {
+            let upgraded = if spell.upgraded { "+" } else { "" };
+
+            text.sections[0].value = format!("{}{upgraded}\n", spell.name);
+            text.sections[1].value = spell.rules_text();
+        }
+    }
+}
+
+#[derive(Component)]
+struct EnergyText;
+
+fn update_energy_text(
+    hands: Query<&Hand>,
+    spells: Query<&Spell>,
+    player: Res<Player>,
+    mut texts: Query<&mut Text, With<EnergyText>>,
+) {
+    let selected: u32 = hands
+        .iter()
+        .flat_map(|x| spells.iter_many(&x.selected_cards))
+        .map(|x| x.cost)
+        .sum();
+
+    let energy = format!("Energy {}/{}", player.energy, player.max_energy);
+    let cost = if selected == 0 {
+        String::new()
+    } else {
+        format!(" -{selected}")
+    };
+
+    for mut text in &mut texts {
+        if text.sections[0].value != energy || text.sections[1].value != cost {
+            text.sections[0].value = energy.clone();
+            text.sections[1].value = cost.clone();
+        }
+    }
+}
+
 fn hover_active_card(mut cards: Query<(&mut Transform, &WorldHover), With<ActiveCard>>) {
     for (mut transform, hover) in &mut cards {
         *transform = if hover.is_cursor_in {
@@ -246,7 +532,8 @@ fn discard(mut discard_piles: Query<&mut Pile, With<DiscardPile>>, mut hands: Qu
 struct Hand {
     cards: Vec<Entity>,
     hovered_card: Option<Entity>,
-    selected_cards: HashSet<Entity>,
+    /// In the order they were picked, which is the order they are merged in.
+    selected_cards: Vec<Entity>,
 }
 
 #[derive(Component, Default)]
@@ -260,6 +547,153 @@ struct DrawPile;
 #[derive(Component, Default)]
 struct DiscardPile;
 
+const PILE_SIZE: Vec2 = Vec2::new(700.0, 1000.0);
+const PILE_BADGE: Vec2 = Vec2::new(0.0, 300.0);
+
+#[derive(Component)]
+struct PileCount;
+
+fn update_pile_counts(
+    piles: Query<(&Pile, &Children), Changed<Pile>>,
+    mut texts: Query<&mut Text, With<PileCount>>,
+) {
+    for (pile, children) in &piles {
+        let mut iter = texts.iter_many_mut(children.iter());
+        while let Some(mut text) = iter.fetch_next() {
+            text.sections[0].value = pile.cards.len().to_string();
+        }
+    }
+}
+
+/// Lists the cards of the pile that was clicked, over the rest of the battle.
+#[derive(Component)]
+struct PileViewer;
+
+#[derive(Component)]
+struct PileViewerText;
+
+fn pile_viewer_closed(viewers: Query<&Visibility, With<PileViewer>>) -> bool {
+    viewers.iter().all(|x| !x.is_visible)
+}
+
+fn view_piles(
+    piles: Query<(&Pile, &WorldHover, Option<&DrawPile>)>,
+    hands: Query<&Hand>,
+    spells: Query<&Spell>,
+    viewers: Query<(Entity, &Visibility), With<PileViewer>>,
+    mut texts: Query<&mut Text, With<PileViewerText>>,
+    mut events: EventReader<MouseButtonInput>,
+    mut commands: Commands,
+) {
+    if !left_clicked(&mut events) {
+        return;
+    }
+
+    // visibility is changed through commands, so the click that closes the viewer is not
+    // also taken as picking a card this frame
+    for (entity, visibility) in &viewers {
+        if visibility.is_visible {
+            commands
+                .entity(entity)
+                .insert(Visibility { is_visible: false });
+            return;
+        }
+    }
+
+    if hands.iter().any(|x| x.hovered_card.is_some()) {
+        return;
+    }
+
+    let (pile, _, draw_pile) = match piles.iter().find(|(_, hover, _)| hover.is_cursor_in) {
+        Some(pile) => pile,
+        None => return,
+    };
+
+    let mut descriptions: Vec<_> = spells
+        .iter_many(&pile.cards)
+        .map(|x| x.description())
+        .collect();
+    // the order of the draw pile is what comes next, so it stays hidden
+    if draw_pile.is_some() {
+        descriptions.sort();
+    }
+
+    let title = if draw_pile.is_some() {
+        "Draw pile"
+    } else {
+        "Discard pile"
+    };
+
+    for mut text in &mut texts {
+        text.sections[0].value = format!("{title} ({})\n", pile.cards.len());
+        text.sections[1].value = if descriptions.is_empty() {
+            "Empty".into()
+        } else {
+            descriptions.join("\n")
+        };
+    }
+
+    for (entity, _) in &viewers {
+        commands
+            .entity(entity)
+            .insert(Visibility { is_visible: true });
+    }
+}
+
+/// Buttons next to the hand, shown while cards are selected.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum CardAction {
+    Cast,
+    Cancel,
+}
+
+struct CardActionPrefab {
+    action: CardAction,
+    transform: Transform,
+    font: Handle<Font>,
+}
+
+impl Prefab for CardActionPrefab {
+    fn construct(self, entity: &mut EntityCommands) {
+        let size = Vec2::new(500.0, 200.0);
+        let label = match self.action {
+            CardAction::Cast => "Cast",
+            CardAction::Cancel => "Cancel",
+        };
+
+        entity
+            .insert_bundle(SpatialBundle {
+                transform: self.transform,
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(WorldHover::new(size))
+            .insert(self.action)
+            .with_children(|p| {
+                p.spawn_bundle(ColorMesh2dBundle {
+                    mesh: square_mesh().into(),
+                    material: white_color_material(),
+                    transform: Transform::from_scale(size.extend(1.0)),
+                    ..default()
+                });
+
+                p.spawn_bundle(Text2dBundle {
+                    text: Text::from_section(
+                        label,
+                        TextStyle {
+                            font: self.font,
+                            font_size: 100.0,
+                            color: Color::BLACK,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER),
+                    transform: Transform::from_xyz(0.0, 0.0, 1.0),
+                    ..default()
+                });
+            });
+    }
+}
+
 pub struct CardsPrefab {
     pub layer: RenderLayers,
     pub transform: Transform,
@@ -280,11 +714,13 @@ impl Prefab for CardsPrefab {
                     .spells
                     .iter()
                     .cloned()
-                    .map(|spell| {
+                    .enumerate()
+                    .map(|(i, spell)| {
                         p.spawn_prefab(CardPrefab {
                             spell,
                             font: self.font.clone(),
                         })
+                        .insert(DeckCard(i))
                         .id()
                     })
                     .collect();
@@ -298,23 +734,172 @@ impl Prefab for CardsPrefab {
                 .insert(WorldHover::new([4000.0, 2500.0].into()))
                 .insert(Hand::default());
 
+                let badge_style = TextStyle {
+                    font: self.font.clone(),
+                    font_size: 100.0,
+                    color: Color::WHITE,
+                };
+
                 p.spawn_bundle(SpatialBundle {
                     transform: Transform::from_xyz(1600.0, -2000.0, 15.0),
                     ..default()
                 })
+                .insert(WorldHover::new(PILE_SIZE))
                 .insert(Pile::default())
-                .insert(DiscardPile);
+                .insert(DiscardPile)
+                .with_children(|p| {
+                    spawn_badge(p, "0".into(), Color::DARK_GRAY, PILE_BADGE, &badge_style)
+                        .insert(PileCount);
+                });
 
                 p.spawn_bundle(SpatialBundle {
                     transform: Transform::from_xyz(-1600.0, -2000.0, 15.0),
                     ..default()
                 })
+                .insert(WorldHover::new(PILE_SIZE))
                 .insert(Pile { cards })
-                .insert(DrawPile);
+                .insert(DrawPile)
+                .with_children(|p| {
+                    spawn_badge(p, "0".into(), Color::DARK_GRAY, PILE_BADGE, &badge_style)
+                        .insert(PileCount);
+                });
+
+                let style = TextStyle {
+                    font: self.font.clone(),
+                    font_size: 120.0,
+                    color: ENERGY_COLOR,
+                };
+
+                p.spawn_bundle(Text2dBundle {
+                    text: Text::from_sections([
+                        TextSection {
+                            value: String::new(),
+                            style: style.clone(),
+                        },
+                        TextSection {
+                            value: String::new(),
+                            style: TextStyle {
+                                color: Color::WHITE,
+                                ..style
+                            },
+                        },
+                    ])
+                    .with_alignment(TextAlignment::CENTER),
+                    transform: Transform::from_xyz(-1700.0, -1100.0, 20.0),
+                    ..default()
+                })
+                .insert(EnergyText);
+
+                p.spawn_bundle(SpatialBundle {
+                    transform: Transform::from_xyz(0.0, 500.0, 40.0),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(CardTooltip)
+                .with_children(|p| {
+                    p.spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(0.0, 0.0, 0.0, 0.85),
+                            custom_size: Some([2600.0, 1100.0].into()),
+                            ..default()
+                        },
+                        ..default()
+                    });
+
+                    let style = TextStyle {
+                        font: self.font.clone(),
+                        font_size: 90.0,
+                        color: Color::WHITE,
+                    };
+
+                    p.spawn_bundle(Text2dBundle {
+                        text: Text::from_sections([
+                            TextSection {
+                                value: String::new(),
+                                style: TextStyle {
+                                    font_size: 130.0,
+                                    ..style.clone()
+                                },
+                            },
+                            TextSection {
+                                value: String::new(),
+                                style,
+                            },
+                        ])
+                        .with_alignment(TextAlignment::TOP_LEFT),
+                        text_2d_bounds: Text2dBounds {
+                            size: [2400.0, 1000.0].into(),
+                        },
+                        transform: Transform::from_xyz(-1200.0, 500.0, 1.0),
+                        ..default()
+                    })
+                    .insert(CardTooltipText);
+                });
+
+                p.spawn_bundle(SpatialBundle {
+                    transform: Transform::from_xyz(0.0, 300.0, 45.0),
+                    visibility: Visibility { is_visible: false },
+                    ..default()
+                })
+                .insert(PileViewer)
+                .with_children(|p| {
+                    p.spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(0.0, 0.0, 0.0, 0.9),
+                            custom_size: Some([3200.0, 3000.0].into()),
+                            ..default()
+                        },
+                        ..default()
+                    });
+
+                    let style = TextStyle {
+                        font: self.font.clone(),
+                        font_size: 70.0,
+                        color: Color::WHITE,
+                    };
+
+                    p.spawn_bundle(Text2dBundle {
+                        text: Text::from_sections([
+                            TextSection {
+                                value: String::new(),
+                                style: TextStyle {
+                                    font_size: 130.0,
+                                    ..style.clone()
+                                },
+                            },
+                            TextSection {
+                                value: String::new(),
+                                style,
+                            },
+                        ])
+                        .with_alignment(TextAlignment::TOP_LEFT),
+                        text_2d_bounds: Text2dBounds {
+                            size: [3000.0, 2900.0].into(),
+                        },
+                        transform: Transform::from_xyz(-1500.0, 1450.0, 1.0),
+                        ..default()
+                    })
+                    .insert(PileViewerText);
+                });
+
+                p.spawn_prefab(CardActionPrefab {
+                    action: CardAction::Cast,
+                    transform: Transform::from_xyz(1700.0, -1000.0, 20.0),
+                    font: self.font.clone(),
+                });
+
+                p.spawn_prefab(CardActionPrefab {
+                    action: CardAction::Cancel,
+                    transform: Transform::from_xyz(1700.0, -1250.0, 20.0),
+                    font: self.font.clone(),
+                });
             });
     }
 }
 
+const UPGRADED_COLOR: Color = Color::rgb(0.1, 0.6, 0.1);
+const ATTACK_COLOR: Color = Color::rgb(0.8, 0.2, 0.2);
+
 pub struct CardPrefab {
     pub font: Handle<Font>,
     pub spell: Spell,
@@ -341,6 +926,7 @@ impl Prefab for CardPrefab {
             .insert_bundle(SpatialBundle::default())
             .insert(WorldHover::new([width, height].into()).extend_bottom_bounds(1000.0))
             .insert(self.spell.clone())
+            .insert(CardTarget::default())
             .with_children(|commands| {
                 commands.spawn_bundle(ColorMesh2dBundle {
                     mesh: square_mesh().into(),
@@ -358,32 +944,144 @@ impl Prefab for CardPrefab {
                     ..default()
                 });
 
-                for element in self.spell.elements.as_ref() {
+                // elements sit side by side, shrinking to fit the card
+                let count = self.spell.elements.len();
+                let icon_size = 400_f32.min((width - 100.0) / count as f32);
+
+                for (i, element) in self.spell.elements.iter().enumerate() {
+                    let x = (i as f32 - (count - 1) as f32 / 2.0) * icon_size;
+
                     commands.spawn_bundle(SpriteBundle {
                         sprite: Sprite {
                             color: element.color() * Vec4::new(1.0, 1.0, 1.0, 0.5),
-                            custom_size: Some([400.0, 400.0].into()),
+                            custom_size: Some([icon_size, icon_size].into()),
                             ..default()
                         },
                         texture: element.icon_handle(),
-                        transform: Transform::from_xyz(0.0, 0.0, 1.0),
+                        transform: Transform::from_xyz(x, 0.0, 1.0),
                         ..default()
                     });
                 }
 
+                let mut name = vec![TextSection {
+                    value: self.spell.name.to_string(),
+                    style: style.clone(),
+                }];
+                if self.spell.upgraded {
+                    name.push(TextSection {
+                        value: "+".into(),
+                        style: TextStyle {
+                            color: UPGRADED_COLOR,
+                            ..style.clone()
+                        },
+                    });
+                }
+
                 commands.spawn_bundle(Text2dBundle {
-                    text: Text::from_section(self.spell.name.to_string(), style.clone())
-                        .with_alignment(alignment),
+                    text: Text::from_sections(name).with_alignment(alignment),
+                    // names of merged spells get long, this keeps them clear of the badges
+                    text_2d_bounds: Text2dBounds {
+                        size: [110.0 * SCALE, height].into(),
+                    },
                     transform: Transform::from_xyz(0.0, 100.0 * SCALE, 2.0),
                     ..default()
                 });
 
-                commands.spawn_bundle(Text2dBundle {
-                    text: Text::from_section(self.spell.attack.to_string(), style)
+                let badge_style = TextStyle {
+                    color: Color::WHITE,
+                    ..style.clone()
+                };
+
+                spawn_badge(
+                    commands,
+                    self.spell.cost.to_string(),
+                    ENERGY_COLOR,
+                    Vec2::new(-68.0, 104.0) * SCALE,
+                    &badge_style,
+                );
+
+                if self.spell.attack != 0 || self.spell.heal == 0 {
+                    spawn_badge(
+                        commands,
+                        self.spell.attack.to_string(),
+                        ATTACK_COLOR,
+                        Vec2::new(-66.0, -105.0) * SCALE,
+                        &badge_style,
+                    );
+                }
+                if self.spell.heal != 0 {
+                    spawn_badge(
+                        commands,
+                        format!("+{}", self.spell.heal),
+                        Element::Heal.color(),
+                        Vec2::new(66.0, -105.0) * SCALE,
+                        &badge_style,
+                    );
+                }
+
+                if self.spell.merge_limit < MAX_MERGE {
+                    commands.spawn_bundle(Text2dBundle {
+                        text: Text::from_section(
+                            self.spell.merge_limit_text(),
+                            TextStyle {
+                                font_size: 12.0 * SCALE,
+                                color: Color::GRAY,
+                                ..style.clone()
+                            },
+                        )
                         .with_alignment(alignment),
-                    transform: Transform::from_xyz(0.0, -70.0 * SCALE, 2.0),
-                    ..default()
-                });
+                        transform: Transform::from_xyz(0.0, 122.0 * SCALE, 2.0),
+                        ..default()
+                    });
+                }
+
+                if !self.spell.effects.is_empty() {
+                    let effects: Vec<_> =
+                        self.spell.effects.iter().map(|x| x.description()).collect();
+
+                    commands.spawn_bundle(Text2dBundle {
+                        text: Text::from_section(
+                            effects.join(", "),
+                            TextStyle {
+                                font_size: 13.0 * SCALE,
+                                ..style
+                            },
+                        )
+                        .with_alignment(alignment),
+                        text_2d_bounds: Text2dBounds {
+                            size: [150.0 * SCALE, height].into(),
+                        },
+                        transform: Transform::from_xyz(0.0, -58.0 * SCALE, 2.0),
+                        ..default()
+                    });
+                }
             });
     }
 }
+
+/// A number on a colored square, returns the text so it can be updated.
+fn spawn_badge<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    value: String,
+    color: Color,
+    position: Vec2,
+    style: &TextStyle,
+) -> EntityCommands<'w, 's, 'a> {
+    let size = style.font_size * 1.4;
+
+    parent.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::splat(size)),
+            ..default()
+        },
+        transform: Transform::from_translation(position.extend(1.5)),
+        ..default()
+    });
+
+    parent.spawn_bundle(Text2dBundle {
+        text: Text::from_section(value, style.clone()).with_alignment(TextAlignment::CENTER),
+        transform: Transform::from_translation(position.extend(2.0)),
+        ..default()
+    })
+}
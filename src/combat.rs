@@ -0,0 +1,803 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::Display;
+
+use crate::{
+    board::{Element, Match},
+    enemies::{BoardHazard, BossPhase, EnemyAction, EnemyDefinition},
+    player::Spell,
+};
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageDealt>()
+            .add_event::<Healed>()
+            .add_event::<Shielded>()
+            .add_event::<EnemyKilled>()
+            .init_resource::<CombatStats>()
+            .init_resource::<Field>()
+            .add_system(track_stats);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum CombatTarget {
+    Player,
+    Enemy(Entity),
+}
+
+/// Sent whenever something loses health, no matter where the damage came from.
+#[derive(Debug, Clone)]
+pub struct DamageDealt {
+    pub target: CombatTarget,
+    pub amount: u32,
+    pub element: Option<Element>,
+    /// Number of matches that contributed to this damage.
+    pub combo: u32,
+    pub critical: bool,
+    /// Damage a shield took instead of health, not included in `amount`.
+    pub absorbed: u32,
+}
+
+/// Sent whenever something regains health.
+#[derive(Debug, Clone)]
+pub struct Healed {
+    pub target: CombatTarget,
+    pub amount: u32,
+    pub combo: u32,
+    pub critical: bool,
+}
+
+/// Sent whenever something gains shield.
+#[derive(Debug, Clone)]
+pub struct Shielded {
+    pub target: CombatTarget,
+    pub amount: u32,
+    pub combo: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnemyKilled {
+    pub enemy: Entity,
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct CombatStats {
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    pub healed: u32,
+    pub damage_blocked: u32,
+    pub enemies_killed: u32,
+}
+
+fn track_stats(
+    mut damage_events: EventReader<DamageDealt>,
+    mut heal_events: EventReader<Healed>,
+    mut kill_events: EventReader<EnemyKilled>,
+    mut stats: ResMut<CombatStats>,
+) {
+    for event in damage_events.iter() {
+        match event.target {
+            CombatTarget::Player => {
+                stats.damage_taken += event.amount;
+                stats.damage_blocked += event.absorbed;
+            }
+            CombatTarget::Enemy(_) => stats.damage_dealt += event.amount,
+        }
+    }
+
+    for event in heal_events.iter() {
+        if event.target == CombatTarget::Player {
+            stats.healed += event.amount;
+        }
+    }
+
+    stats.enemies_killed += kill_events.iter().count() as u32;
+}
+
+/// Healing every Heal gem gives, on top of the spell's heal.
+pub const BASE_HEAL_PER_GEM: u32 = 1;
+pub const SHIELD_PER_GEM: u32 = 2;
+/// Part of any healing past max health that becomes shield instead.
+pub const OVERHEAL_SHIELD: f32 = 0.5;
+/// Part of the shield that is kept at the start of each player turn.
+pub const SHIELD_KEPT: f32 = 0.5;
+pub const COMBO_BONUS: f32 = 0.1;
+pub const CRITICAL_MATCH_SIZE: u32 = 5;
+pub const CRITICAL_MULTIPLIER: f32 = 1.5;
+pub const STRONG_MULTIPLIER: f32 = 1.5;
+pub const WEAK_MULTIPLIER: f32 = 0.5;
+pub const VULNERABLE_MULTIPLIER: f32 = 1.5;
+pub const BLESSED_MULTIPLIER: f32 = 1.5;
+pub const BLINDED_MULTIPLIER: f32 = 0.5;
+pub const DRAINED_ENERGY: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Display, Deserialize, Serialize)]
+pub enum StatusKind {
+    /// Takes more damage from every source.
+    Vulnerable,
+    /// Heals for more from every source.
+    Blessed,
+    /// Deals less damage with every attack.
+    Blinded,
+    /// Gets less energy at the start of the turn.
+    Drained,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Status {
+    pub kind: StatusKind,
+    /// Number of times [`Statuses::tick`] has to be called before the status wears off.
+    pub turns: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Statuses(pub Vec<Status>);
+
+impl Statuses {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.0.iter().any(|x| x.kind == kind)
+    }
+
+    /// Adds a status or refreshes its duration if it is already applied.
+    pub fn apply(&mut self, status: Status) {
+        match self.0.iter_mut().find(|x| x.kind == status.kind) {
+            Some(existing) => existing.turns = existing.turns.max(status.turns),
+            None => self.0.push(status),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        for status in &mut self.0 {
+            status.turns = status.turns.saturating_sub(1);
+        }
+
+        self.0.retain(|x| x.turns > 0);
+    }
+
+    pub fn damage_taken_multiplier(&self) -> f32 {
+        if self.has(StatusKind::Vulnerable) {
+            VULNERABLE_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+
+    pub fn damage_dealt_multiplier(&self) -> f32 {
+        if self.has(StatusKind::Blinded) {
+            BLINDED_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+
+    pub fn energy_penalty(&self) -> u32 {
+        if self.has(StatusKind::Drained) {
+            DRAINED_ENERGY
+        } else {
+            0
+        }
+    }
+
+    pub fn healing_multiplier(&self) -> f32 {
+        if self.has(StatusKind::Blessed) {
+            BLESSED_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+}
+
+/// One thing a spell does besides its attack and heal. Effects run in the order the spell lists
+/// them, those that change the board when the spell is cast, see [`spell_cast`], the rest when
+/// it resolves.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum SpellEffect {
+    /// Multiplies the damage the spell does with the element.
+    Amplify { element: Element, multiplier: f32 },
+    /// Restores health on top of what Heal gems restore.
+    Heal(u32),
+    /// Shields the player on top of what Light gems give.
+    Shield(u32),
+    /// Applies a status to the enemies the spell hits.
+    Apply(Status),
+    /// Draws extra cards next turn.
+    Draw(u32),
+    /// Gives more seconds to move gems.
+    MoveTime(f32),
+    /// Turns up to `gems` random gems of one element into another before the move.
+    Convert {
+        from: Element,
+        to: Element,
+        gems: u32,
+    },
+    /// Ignores enemies resisting the spell's elements.
+    Pierce,
+}
+
+impl SpellEffect {
+    pub fn description(&self) -> String {
+        match self {
+            SpellEffect::Amplify {
+                element,
+                multiplier,
+            } => {
+                let percentage = ((multiplier - 1.0) * 100.0).round() as i32;
+                format!("{element} {percentage:+}%")
+            }
+            SpellEffect::Heal(amount) => format!("Heal {amount}"),
+            SpellEffect::Shield(amount) => format!("Shield {amount}"),
+            SpellEffect::Apply(status) => status.kind.to_string(),
+            SpellEffect::Draw(cards) => format!("Draw {cards}"),
+            SpellEffect::MoveTime(seconds) => format!("{seconds:+}s"),
+            SpellEffect::Convert { from, to, gems } => format!("{gems} {from} to {to}"),
+            SpellEffect::Pierce => "Pierce".into(),
+        }
+    }
+
+    /// A full sentence explaining the effect.
+    pub fn rules_text(&self) -> String {
+        let plural = |n: u32| if n == 1 { "" } else { "s" };
+
+        match self {
+            SpellEffect::Amplify {
+                element,
+                multiplier,
+            } => format!("{element} damage is multiplied by {multiplier}."),
+            SpellEffect::Heal(amount) => format!("Heals {amount}, even without Heal gems."),
+            SpellEffect::Shield(amount) => format!("Gives {amount} shield."),
+            SpellEffect::Apply(status) => format!(
+                "Enemies hit become {} for {} turn{}.",
+                status.kind,
+                status.turns,
+                plural(status.turns)
+            ),
+            SpellEffect::Draw(cards) => {
+                format!("Draw {cards} more card{} next turn.", plural(*cards))
+            }
+            SpellEffect::MoveTime(seconds) => format!("{seconds:+} seconds to move gems."),
+            SpellEffect::Convert { from, to, gems } => {
+                format!("Turns {gems} {from} gems into {to} before the move.")
+            }
+            SpellEffect::Pierce => "Ignores enemies resisting its elements.".into(),
+        }
+    }
+}
+
+/// A named effect an environment has on every battle fought in it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldEffect {
+    pub name: String,
+    pub kind: FieldEffectKind,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum FieldEffectKind {
+    /// Multiplies all damage done with the element.
+    Empower { element: Element, multiplier: f32 },
+    /// Makes skyfall drop the element `weight` times as often as the others.
+    Favour { element: Element, weight: f32 },
+    /// Gems of the element are drawn without their color.
+    Hide { element: Element },
+}
+
+impl FieldEffectKind {
+    pub fn description(&self) -> String {
+        match self {
+            FieldEffectKind::Empower {
+                element,
+                multiplier,
+            } => {
+                let percentage = ((multiplier - 1.0) * 100.0).round() as i32;
+                format!("{element} damage {percentage:+}%")
+            }
+            FieldEffectKind::Favour { element, .. } => format!("Skyfall favours {element}"),
+            FieldEffectKind::Hide { element } => format!("{element} gems are hidden"),
+        }
+    }
+}
+
+/// The field effects of the current battle.
+#[derive(Debug, Clone, Default)]
+pub struct Field {
+    pub effects: Vec<FieldEffect>,
+}
+
+impl Field {
+    pub fn damage_multiplier(&self, element: Element) -> f32 {
+        self.effects
+            .iter()
+            .filter_map(|x| match x.kind {
+                FieldEffectKind::Empower {
+                    element: empowered,
+                    multiplier,
+                } if empowered == element => Some(multiplier),
+                _ => None,
+            })
+            .product()
+    }
+
+    pub fn skyfall_weight(&self, element: Element) -> f32 {
+        self.effects
+            .iter()
+            .filter_map(|x| match x.kind {
+                FieldEffectKind::Favour {
+                    element: favoured,
+                    weight,
+                } if favoured == element => Some(weight),
+                _ => None,
+            })
+            .product()
+    }
+
+    pub fn is_hidden(&self, element: Element) -> bool {
+        self.effects.iter().any(
+            |x| matches!(x.kind, FieldEffectKind::Hide { element: hidden } if hidden == element),
+        )
+    }
+
+    /// Picks the element of a gem falling onto the board.
+    pub fn skyfall(&self, rng: &fastrand::Rng) -> Element {
+        let total: f32 = Element::iter().map(|x| self.skyfall_weight(x)).sum();
+        let mut roll = rng.f32() * total;
+
+        for element in Element::iter() {
+            roll -= self.skyfall_weight(element);
+
+            if roll < 0.0 {
+                return element;
+            }
+        }
+
+        Element::random_with(rng)
+    }
+}
+
+/// The parts of a [`Match`] that matter for combat.
+#[derive(Debug, Clone, Copy)]
+pub struct MatchedGems {
+    pub element: Element,
+    pub count: u32,
+}
+
+impl From<&Match> for MatchedGems {
+    fn from(value: &Match) -> Self {
+        Self {
+            element: value.element,
+            count: value.tiles.len() as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ElementDamage {
+    pub element: Element,
+    pub gems: u32,
+    pub matches: u32,
+    pub critical: bool,
+    pub multiplier: f32,
+    pub amount: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DamageBreakdown {
+    pub hits: Vec<ElementDamage>,
+    pub combos: u32,
+    pub combo_multiplier: f32,
+    pub applied_statuses: Vec<Status>,
+}
+
+impl DamageBreakdown {
+    pub fn total(&self) -> u32 {
+        self.hits.iter().map(|x| x.amount).sum()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HealBreakdown {
+    pub gems: u32,
+    pub matches: u32,
+    pub critical: bool,
+    pub multiplier: f32,
+    pub amount: u32,
+    pub applied_statuses: Vec<Status>,
+}
+
+/// What casting a spell changes before the player moves.
+#[derive(Debug, Clone, Default)]
+pub struct CastBreakdown {
+    pub move_time: f32,
+    pub draws: u32,
+    /// The element to convert, the element it becomes and the number of gems, in order.
+    pub conversions: Vec<(Element, Element, u32)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ShieldBreakdown {
+    pub gems: u32,
+    pub matches: u32,
+    pub amount: u32,
+}
+
+pub fn combo_multiplier(combos: u32) -> f32 {
+    1.0 + COMBO_BONUS * combos.saturating_sub(1) as f32
+}
+
+/// Water beats Fire, Fire beats Grass, Grass beats Water and Light and Dark beat each other.
+pub fn affinity_multiplier(attack: Element, affinity: Option<Element>) -> f32 {
+    use Element::*;
+
+    match (attack, affinity) {
+        (Water, Some(Fire))
+        | (Fire, Some(Grass))
+        | (Grass, Some(Water))
+        | (Light, Some(Dark))
+        | (Dark, Some(Light)) => STRONG_MULTIPLIER,
+        (Fire, Some(Water)) | (Grass, Some(Fire)) | (Water, Some(Grass)) => WEAK_MULTIPLIER,
+        _ => 1.0,
+    }
+}
+
+pub fn element_status(element: Element) -> Option<Status> {
+    match element {
+        Element::Dark => Some(Status {
+            kind: StatusKind::Vulnerable,
+            turns: 1,
+        }),
+        _ => None,
+    }
+}
+
+/// Runs the effects of a spell that happen as soon as it's cast.
+pub fn spell_cast(spell: &Spell) -> CastBreakdown {
+    let mut breakdown = CastBreakdown::default();
+
+    for effect in spell.effects.iter() {
+        match *effect {
+            SpellEffect::Draw(cards) => breakdown.draws += cards,
+            SpellEffect::MoveTime(seconds) => breakdown.move_time += seconds,
+            SpellEffect::Convert { from, to, gems } => {
+                breakdown.conversions.push((from, to, gems));
+            }
+            _ => {}
+        }
+    }
+
+    breakdown
+}
+
+pub fn spell_damage(
+    spell: &Spell,
+    matches: &[MatchedGems],
+    defender: &Statuses,
+    affinity: Option<Element>,
+    field: &Field,
+) -> DamageBreakdown {
+    let combos = matches.len() as u32;
+    let combo_multiplier = combo_multiplier(combos);
+    let pierce = spell.effects.contains(&SpellEffect::Pierce);
+
+    // Heal gems heal instead, see [`spell_heal`]
+    let hits: Vec<_> = Element::iter()
+        .filter(|element| *element != Element::Heal && spell.elements.contains(element))
+        .filter_map(|element| {
+            let element_matches: Vec<_> = matches.iter().filter(|x| x.element == element).collect();
+
+            if element_matches.is_empty() {
+                return None;
+            }
+
+            let critical = element_matches
+                .iter()
+                .any(|x| x.count >= CRITICAL_MATCH_SIZE);
+            let base: f32 = element_matches
+                .iter()
+                .map(|x| {
+                    let damage = (x.count * spell.attack) as f32;
+
+                    if x.count >= CRITICAL_MATCH_SIZE {
+                        damage * CRITICAL_MULTIPLIER
+                    } else {
+                        damage
+                    }
+                })
+                .sum();
+
+            let affinity = affinity_multiplier(element, affinity);
+            let amplify: f32 = spell
+                .effects
+                .iter()
+                .filter_map(|x| match *x {
+                    SpellEffect::Amplify {
+                        element: amplified,
+                        multiplier,
+                    } if amplified == element => Some(multiplier),
+                    _ => None,
+                })
+                .product();
+
+            let multiplier = combo_multiplier
+                * if pierce { affinity.max(1.0) } else { affinity }
+                * defender.damage_taken_multiplier()
+                * field.damage_multiplier(element)
+                * amplify;
+
+            Some(ElementDamage {
+                element,
+                gems: element_matches.iter().map(|x| x.count).sum(),
+                matches: element_matches.len() as u32,
+                critical,
+                multiplier,
+                amount: (base * multiplier).round() as u32,
+            })
+        })
+        .collect();
+
+    let mut applied_statuses: Vec<_> = hits
+        .iter()
+        .filter_map(|x| element_status(x.element))
+        .collect();
+
+    if !hits.is_empty() {
+        applied_statuses.extend(spell.effects.iter().filter_map(|x| match x {
+            SpellEffect::Apply(status) => Some(*status),
+            _ => None,
+        }));
+    }
+
+    DamageBreakdown {
+        hits,
+        combos,
+        combo_multiplier,
+        applied_statuses,
+    }
+}
+
+/// Heal gems heal a little with any spell and a lot with spells that heal.
+/// Critical heals with a Heal spell bless the healer.
+pub fn spell_heal(spell: &Spell, matches: &[MatchedGems], healer: &Statuses) -> HealBreakdown {
+    let heal_matches: Vec<_> = matches
+        .iter()
+        .filter(|x| x.element == Element::Heal)
+        .collect();
+    let gems = heal_matches.iter().map(|x| x.count).sum::<u32>();
+    // heal effects don't need any gems or get any combo bonus
+    let flat: u32 = spell
+        .effects
+        .iter()
+        .map(|x| match x {
+            SpellEffect::Heal(amount) => *amount,
+            _ => 0,
+        })
+        .sum();
+
+    if gems == 0 && flat == 0 {
+        return default();
+    }
+
+    let critical = heal_matches.iter().any(|x| x.count >= CRITICAL_MATCH_SIZE);
+    let base: f32 = heal_matches
+        .iter()
+        .map(|x| {
+            let heal = (x.count * (BASE_HEAL_PER_GEM + spell.heal)) as f32;
+
+            if x.count >= CRITICAL_MATCH_SIZE {
+                heal * CRITICAL_MULTIPLIER
+            } else {
+                heal
+            }
+        })
+        .sum();
+
+    let multiplier = combo_multiplier(matches.len() as u32) * healer.healing_multiplier();
+
+    let applied_statuses = if critical && spell.elements.contains(&Element::Heal) {
+        vec![Status {
+            kind: StatusKind::Blessed,
+            turns: 2,
+        }]
+    } else {
+        vec![]
+    };
+
+    HealBreakdown {
+        gems,
+        matches: heal_matches.len() as u32,
+        critical,
+        multiplier,
+        amount: (base * multiplier + flat as f32 * healer.healing_multiplier()).round() as u32,
+        applied_statuses,
+    }
+}
+
+/// Light gems and shield effects shield the player.
+pub fn spell_shield(spell: &Spell, matches: &[MatchedGems]) -> ShieldBreakdown {
+    let shield_matches: Vec<_> = matches
+        .iter()
+        .filter(|x| x.element == Element::Light)
+        .collect();
+    let gems = shield_matches.iter().map(|x| x.count).sum::<u32>();
+    let flat: u32 = spell
+        .effects
+        .iter()
+        .map(|x| match x {
+            SpellEffect::Shield(amount) => *amount,
+            _ => 0,
+        })
+        .sum();
+
+    ShieldBreakdown {
+        gems,
+        matches: shield_matches.len() as u32,
+        amount: gems * SHIELD_PER_GEM + flat,
+    }
+}
+
+pub fn enemy_damage(attack: u32, defender: &Statuses) -> u32 {
+    (attack as f32 * defender.damage_taken_multiplier()).round() as u32
+}
+
+/// The combat side of an enemy, shared by the battle and the simulator.
+#[derive(Debug, Clone)]
+pub struct EnemyState {
+    pub definition: EnemyDefinition,
+    pub max_health: u32,
+    pub current_health: u32,
+    pub statuses: Statuses,
+    attack: u32,
+    phase: usize,
+    turn: usize,
+    charged: bool,
+}
+
+/// What an enemy did on its turn.
+#[derive(Debug, Clone)]
+pub struct EnemyTurn {
+    pub action: EnemyAction,
+    /// None when the enemy spent its turn charging.
+    pub damage: Option<u32>,
+    pub applied_statuses: Vec<Status>,
+}
+
+impl EnemyState {
+    pub fn new(definition: EnemyDefinition, max_health: u32, attack: u32) -> Self {
+        Self {
+            definition,
+            max_health,
+            current_health: max_health,
+            statuses: default(),
+            attack,
+            phase: 0,
+            turn: 0,
+            charged: false,
+        }
+    }
+
+    pub fn boss_phase(&self) -> Option<&BossPhase> {
+        self.definition
+            .boss
+            .as_ref()
+            .map(|boss| &boss.phases[self.phase])
+    }
+
+    pub fn hazard(&self) -> Option<BoardHazard> {
+        self.boss_phase().and_then(|x| x.hazard)
+    }
+
+    pub fn attack(&self) -> u32 {
+        let multiplier = self
+            .boss_phase()
+            .map(|x| x.attack_multiplier)
+            .unwrap_or(1.0);
+
+        (self.attack as f32 * multiplier).round() as u32
+    }
+
+    fn behaviour(&self) -> &[EnemyAction] {
+        self.boss_phase()
+            .map(|x| x.behaviour.as_slice())
+            .unwrap_or(self.definition.behaviour.as_slice())
+    }
+
+    fn next_action(&mut self) -> EnemyAction {
+        let behaviour = self.behaviour();
+        let action = behaviour[self.turn % behaviour.len()];
+
+        self.turn += 1;
+
+        action
+    }
+
+    /// Moves a boss to the phase matching its current health. Phases never go backwards.
+    fn update_phase(&mut self) {
+        let health = self.current_health as f32 / self.max_health as f32;
+
+        let phase = match &self.definition.boss {
+            Some(boss) => boss
+                .phases
+                .iter()
+                .rposition(|x| health <= x.threshold)
+                .unwrap_or_default(),
+            None => return,
+        };
+
+        if phase > self.phase {
+            self.phase = phase;
+            self.turn = 0;
+            self.charged = false;
+        }
+    }
+
+    /// Hits the enemy with a spell and returns how much damage each element did.
+    pub fn take_spell(
+        &mut self,
+        spell: &Spell,
+        matches: &[MatchedGems],
+        field: &Field,
+    ) -> DamageBreakdown {
+        let breakdown = spell_damage(
+            spell,
+            matches,
+            &self.statuses,
+            self.definition.affinity,
+            field,
+        );
+
+        self.statuses.tick();
+        for status in &breakdown.applied_statuses {
+            self.statuses.apply(*status);
+        }
+
+        self.current_health = self.current_health.saturating_sub(breakdown.total());
+        self.update_phase();
+
+        breakdown
+    }
+
+    /// Takes the next action in the enemy's behaviour against a player with the given statuses.
+    pub fn take_turn(&mut self, player: &Statuses) -> EnemyTurn {
+        let action = self.next_action();
+
+        let attack = match action {
+            EnemyAction::Charge => {
+                self.charged = true;
+
+                return EnemyTurn {
+                    action,
+                    damage: None,
+                    applied_statuses: vec![],
+                };
+            }
+            EnemyAction::Attack => self.attack(),
+            EnemyAction::Curse | EnemyAction::Drain => self.attack() / 2,
+        };
+
+        let attack = if std::mem::take(&mut self.charged) {
+            attack * 2
+        } else {
+            attack
+        };
+        let attack = (attack as f32 * self.statuses.damage_dealt_multiplier()).round() as u32;
+
+        let applied_statuses = match action {
+            EnemyAction::Curse => vec![Status {
+                kind: StatusKind::Vulnerable,
+                turns: 1,
+            }],
+            EnemyAction::Drain => vec![Status {
+                kind: StatusKind::Drained,
+                turns: 1,
+            }],
+            _ => vec![],
+        };
+
+        EnemyTurn {
+            action,
+            damage: Some(enemy_damage(attack, player)),
+            applied_statuses,
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use std::path::Path;
+
+use anyhow::{ensure, Context};
+use bevy::{
+    asset::{AssetLoader, HandleId, LoadContext, LoadState, LoadedAsset},
+    gltf::Gltf,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+use crate::{board::Element, utils::Loading};
+
+pub const ROSTER_PATH: &str = "enemies/enemies.roster.ron";
+
+pub struct EnemiesPlugin;
+
+impl Plugin for EnemiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<EnemyRoster>()
+            .init_asset_loader::<EnemyRosterLoader>()
+            .add_startup_system(load_enemy_roster)
+            .add_system(read_enemy_roster)
+            .add_system(report_missing_animations);
+    }
+}
+
+/// Everything needed to spawn an enemy. Each enemy lives in its own `*.enemy.ron` file
+/// which is listed in `enemies/enemies.roster.ron`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnemyDefinition {
+    pub name: String,
+    /// Path to the glb file, relative to the assets folder.
+    pub model: String,
+    #[serde(default)]
+    pub animations: EnemyAnimationNames,
+    pub health: u32,
+    pub attack: u32,
+    #[serde(default)]
+    pub affinity: Option<Element>,
+    /// Actions taken on each enemy turn, repeated once the end is reached.
+    pub behaviour: Vec<EnemyAction>,
+    /// First and last round (inclusive) that this enemy can appear in.
+    pub rounds: (u32, u32),
+    /// Environments of the same biome are preferred for this enemy's battles.
+    #[serde(default)]
+    pub biome: Option<String>,
+    /// Bosses only show up in the final round.
+    #[serde(default)]
+    pub boss: Option<BossDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BossDefinition {
+    pub title: String,
+    pub scale: f32,
+    /// Ordered from the first phase to the last.
+    pub phases: Vec<BossPhase>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BossPhase {
+    pub name: String,
+    /// The phase starts once the boss' health drops to this fraction of its max health.
+    pub threshold: f32,
+    /// Replaces the enemy's behaviour during this phase.
+    pub behaviour: Vec<EnemyAction>,
+    #[serde(default = "default_attack_multiplier")]
+    pub attack_multiplier: f32,
+    #[serde(default)]
+    pub hazard: Option<BoardHazard>,
+}
+
+fn default_attack_multiplier() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum BoardHazard {
+    /// Turns random gems into the given element at the start of every player turn.
+    Corrupt { element: Element, gems: u32 },
+    /// Takes seconds away from the time the player has to move gems.
+    Haste { seconds: f32 },
+}
+
+/// Clip names to look for in the model, tried in order before the built in names.
+/// Missing hurt, attack and death clips are replaced by procedural animations.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EnemyAnimationNames {
+    pub idle: Vec<String>,
+    pub hurt: Vec<String>,
+    pub attack: Vec<String>,
+    pub death: Vec<String>,
+}
+
+impl EnemyAnimationNames {
+    const DEFAULT_IDLE: &'static [&'static str] = &["Idle", "Flying"];
+    const DEFAULT_HURT: &'static [&'static str] = &["HitRecieve", "HitReceive", "Hit"];
+    const DEFAULT_ATTACK: &'static [&'static str] = &["Bite_Front", "Bite_InPlace", "Attack"];
+    const DEFAULT_DEATH: &'static [&'static str] = &["Death"];
+
+    pub fn candidates(&self, animation: EnemyAnimation) -> impl Iterator<Item = &str> {
+        let (names, defaults) = match animation {
+            EnemyAnimation::Idle => (&self.idle, Self::DEFAULT_IDLE),
+            EnemyAnimation::Hurt => (&self.hurt, Self::DEFAULT_HURT),
+            EnemyAnimation::Attack => (&self.attack, Self::DEFAULT_ATTACK),
+            EnemyAnimation::Death => (&self.death, Self::DEFAULT_DEATH),
+        };
+
+        names
+            .iter()
+            .map(String::as_str)
+            .chain(defaults.iter().copied())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+#[strum(serialize_all = "lowercase")]
+pub enum EnemyAnimation {
+    Idle,
+    Hurt,
+    Attack,
+    Death,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum EnemyAction {
+    Attack,
+    /// Skip a turn to double the damage of the next attack.
+    Charge,
+    /// Half damage attack that leaves the player vulnerable.
+    Curse,
+    /// Half damage attack that leaves the player with less energy next turn.
+    Drain,
+}
+
+impl EnemyDefinition {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let definition: Self = ron::de::from_bytes(bytes)?;
+        definition.validate()?;
+
+        Ok(definition)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(!self.name.is_empty(), "name is empty");
+        ensure!(
+            self.model.ends_with(".glb"),
+            "model {:?} is not a glb file",
+            self.model
+        );
+        ensure!(self.health > 0, "health must be greater than 0");
+        ensure!(!self.behaviour.is_empty(), "behaviour is empty");
+
+        let (first, last) = self.rounds;
+        ensure!(
+            first >= 1 && first <= last,
+            "rounds ({first}, {last}) is not a valid range"
+        );
+
+        if let Some(boss) = &self.boss {
+            ensure!(boss.scale > 0.0, "boss scale must be greater than 0");
+            ensure!(!boss.phases.is_empty(), "bosses need at least one phase");
+            ensure!(
+                boss.phases[0].threshold == 1.0,
+                "the first boss phase needs a threshold of 1.0"
+            );
+            ensure!(
+                boss.phases
+                    .windows(2)
+                    .all(|x| x[1].threshold < x[0].threshold && x[1].threshold > 0.0),
+                "boss phase thresholds need to be decreasing and greater than 0"
+            );
+            ensure!(
+                boss.phases.iter().all(|x| !x.behaviour.is_empty()),
+                "boss phase behaviour is empty"
+            );
+        }
+
+        for animation in EnemyAnimation::iter() {
+            ensure!(
+                self.animations.candidates(animation).all(|x| !x.is_empty()),
+                "animation names can not be empty"
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn appears_in(&self, round: u32) -> bool {
+        (self.rounds.0..=self.rounds.1).contains(&round)
+    }
+
+    pub fn scene_handle(&self) -> Handle<Scene> {
+        let path = format!("{}#Scene0", self.model);
+
+        Handle::weak(HandleId::AssetPathId(path.as_str().into()))
+    }
+
+    pub fn gltf_handle(&self) -> Handle<Gltf> {
+        Handle::weak(HandleId::AssetPathId(self.model.as_str().into()))
+    }
+
+    /// Returns the first clip in the model matching one of the animation's candidate names.
+    pub fn find_animation(
+        &self,
+        gltf: &Gltf,
+        animation: EnemyAnimation,
+    ) -> Option<Handle<AnimationClip>> {
+        self.animations
+            .candidates(animation)
+            .find_map(|name| gltf.named_animations.get(name))
+            .cloned()
+    }
+}
+
+#[derive(Debug, TypeUuid)]
+#[uuid = "5b1f4a0e-7d2c-4b8e-9a57-3c1e6f0d2a41"]
+pub struct EnemyRoster {
+    pub enemies: Vec<EnemyDefinition>,
+}
+
+#[derive(Default)]
+struct EnemyRosterLoader;
+
+impl AssetLoader for EnemyRosterLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let paths: Vec<String> = ron::de::from_bytes(bytes)?;
+
+            let mut enemies = Vec::new();
+            for path in paths {
+                // a broken enemy should not stop the rest from loading
+                let bytes = match load_context.read_asset_bytes(&path).await {
+                    Ok(bytes) => bytes,
+                    Err(error) => {
+                        error!("failed to read enemy definition {path}: {error}");
+                        continue;
+                    }
+                };
+
+                match EnemyDefinition::parse(&bytes) {
+                    Ok(definition) => enemies.push(definition),
+                    Err(error) => error!("invalid enemy definition {path}: {error}"),
+                }
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(EnemyRoster { enemies }));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["roster.ron"]
+    }
+}
+
+/// The enemies that can be spawned, available once [`Enemies::is_ready`] is true.
+pub struct Enemies {
+    roster: Handle<EnemyRoster>,
+    definitions: Vec<EnemyDefinition>,
+    ready: bool,
+}
+
+impl Enemies {
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    pub fn random(&self, round: u32) -> &EnemyDefinition {
+        random_enemy(&self.definitions, round, &fastrand::Rng::new())
+    }
+
+    pub fn random_boss(&self, round: u32) -> &EnemyDefinition {
+        random_boss(&self.definitions, round, &fastrand::Rng::new())
+    }
+}
+
+/// Picks a random regular enemy that can appear in the given round.
+/// Falls back to any enemy if none are meant for that round.
+pub fn random_enemy<'a>(
+    definitions: &'a [EnemyDefinition],
+    round: u32,
+    rng: &fastrand::Rng,
+) -> &'a EnemyDefinition {
+    let candidates: Vec<_> = definitions
+        .iter()
+        .filter(|x| x.boss.is_none() && x.appears_in(round))
+        .collect();
+
+    if candidates.is_empty() {
+        &definitions[rng.usize(..definitions.len())]
+    } else {
+        candidates[rng.usize(..candidates.len())]
+    }
+}
+
+/// Picks a random boss for the given round, or a regular enemy if there are no bosses.
+pub fn random_boss<'a>(
+    definitions: &'a [EnemyDefinition],
+    round: u32,
+    rng: &fastrand::Rng,
+) -> &'a EnemyDefinition {
+    let candidates: Vec<_> = definitions
+        .iter()
+        .filter(|x| x.boss.is_some() && x.appears_in(round))
+        .collect();
+
+    if candidates.is_empty() {
+        random_enemy(definitions, round, rng)
+    } else {
+        candidates[rng.usize(..candidates.len())]
+    }
+}
+
+/// Reads the roster and every enemy in it straight from the assets folder,
+/// for tools that run without an asset server.
+pub fn read_roster_file(assets: &Path, roster: &str) -> anyhow::Result<Vec<EnemyDefinition>> {
+    let paths: Vec<String> = ron::de::from_bytes(&std::fs::read(assets.join(roster))?)?;
+
+    paths
+        .iter()
+        .map(|path| {
+            let bytes = std::fs::read(assets.join(path))?;
+
+            EnemyDefinition::parse(&bytes)
+                .with_context(|| format!("invalid enemy definition {path}"))
+        })
+        .collect()
+}
+
+fn load_enemy_roster(
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<Loading>,
+    mut commands: Commands,
+) {
+    let roster = asset_server.load(ROSTER_PATH);
+
+    loading.assets.push(roster.clone_untyped());
+
+    commands.insert_resource(Enemies {
+        roster,
+        definitions: vec![],
+        ready: false,
+    });
+}
+
+fn read_enemy_roster(
+    mut enemies: ResMut<Enemies>,
+    rosters: Res<Assets<EnemyRoster>>,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<Loading>,
+    mut reported: Local<bool>,
+) {
+    if enemies.ready || *reported {
+        return;
+    }
+
+    // there is nothing to fight without enemies, so the game can't leave the load screen
+    if let Some(roster) = rosters.get(&enemies.roster) {
+        if roster.enemies.is_empty() {
+            error!("no valid enemies were found in the enemy roster");
+            loading
+                .errors
+                .push(format!("No valid enemies were found in {ROSTER_PATH}"));
+            *reported = true;
+
+            return;
+        }
+
+        loading.assets.extend(
+            roster
+                .enemies
+                .iter()
+                .map(|x| asset_server.load_untyped(&x.model)),
+        );
+
+        enemies.definitions = roster.enemies.clone();
+        enemies.ready = true;
+    } else if asset_server.get_load_state(&enemies.roster) == LoadState::Failed {
+        error!("failed to load the enemy roster");
+        loading
+            .errors
+            .push(format!("The enemy roster {ROSTER_PATH} failed to load"));
+        *reported = true;
+    }
+}
+
+/// Lists the clips each enemy model is missing once all of them are loaded,
+/// so broken models are noticed without having to fight every enemy.
+fn report_missing_animations(
+    enemies: Res<Enemies>,
+    gltfs: Res<Assets<Gltf>>,
+    asset_server: Res<AssetServer>,
+    mut reported: Local<bool>,
+) {
+    if *reported || !enemies.ready {
+        return;
+    }
+
+    let still_loading = enemies.definitions.iter().any(|x| {
+        matches!(
+            asset_server.get_load_state(&x.gltf_handle()),
+            LoadState::NotLoaded | LoadState::Loading
+        )
+    });
+
+    if still_loading {
+        return;
+    }
+
+    *reported = true;
+
+    let mut report = String::new();
+    for definition in &enemies.definitions {
+        let gltf = match gltfs.get(&definition.gltf_handle()) {
+            Some(gltf) => gltf,
+            None => {
+                error!("failed to load enemy model {}", definition.model);
+                continue;
+            }
+        };
+
+        for animation in EnemyAnimation::iter() {
+            if definition.find_animation(gltf, animation).is_none() {
+                let tried: Vec<_> = definition.animations.candidates(animation).collect();
+
+                report += &format!(
+                    "\n  {}: no {animation} clip (tried {})",
+                    definition.model,
+                    tried.join(", ")
+                );
+            }
+        }
+    }
+
+    if !report.is_empty() {
+        warn!("enemy models are missing animation clips, procedural animations will be used instead:{report}");
+    }
+}
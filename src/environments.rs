@@ -0,0 +1,346 @@
+use anyhow::ensure;
+use bevy::{
+    asset::{AssetLoader, HandleId, LoadContext, LoadState, LoadedAsset},
+    gltf::{Gltf, GltfNode},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    combat::{Field, FieldEffect, FieldEffectKind},
+    utils::Loading,
+};
+
+const LIST_PATH: &str = "environments/battle.environments.ron";
+
+pub struct EnvironmentsPlugin;
+
+impl Plugin for EnvironmentsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<EnvironmentList>()
+            .init_asset_loader::<EnvironmentListLoader>()
+            .add_startup_system(load_environment_list)
+            .add_system(read_environment_list);
+    }
+}
+
+/// A place battles happen in, listed in `environments/battle.environments.ron`.
+///
+/// Nodes in the glb named `Camera`, `Light` and `EnemySpawn*` take precedence over
+/// the placements written in the definition.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnvironmentDefinition {
+    pub name: String,
+    /// Path to the glb file, relative to the assets folder.
+    pub scene: String,
+    /// Enemies with the same biome prefer this environment.
+    #[serde(default)]
+    pub biome: Option<String>,
+    /// First and last round (inclusive) that this environment can be used in.
+    pub rounds: (u32, u32),
+    #[serde(default)]
+    pub camera: CameraPlacement,
+    #[serde(default)]
+    pub light: LightSettings,
+    #[serde(default = "default_enemy_spawns")]
+    pub enemy_spawns: Vec<Vec3>,
+    #[serde(default)]
+    pub field_effects: Vec<FieldEffect>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CameraPlacement {
+    pub translation: Vec3,
+    pub looking_at: Vec3,
+}
+
+impl Default for CameraPlacement {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::new(0.0, 5.0, 13.0),
+            looking_at: Vec3::new(0.0, 0.0, 5.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LightSettings {
+    pub translation: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    pub color: (f32, f32, f32),
+}
+
+impl Default for LightSettings {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::new(10.0, 10.0, 10.0),
+            intensity: 100000.0,
+            range: 50.0,
+            color: (1.0, 1.0, 1.0),
+        }
+    }
+}
+
+fn default_enemy_spawns() -> Vec<Vec3> {
+    vec![Vec3::ZERO]
+}
+
+/// Where everything goes in an environment, after applying the glb's named nodes.
+#[derive(Clone)]
+pub struct EnvironmentLayout {
+    pub scene: Handle<Scene>,
+    pub camera: Transform,
+    pub light: Transform,
+    pub point_light: PointLight,
+    pub enemy_spawns: Vec<Transform>,
+}
+
+impl EnvironmentDefinition {
+    fn validate(&self) -> anyhow::Result<()> {
+        ensure!(!self.name.is_empty(), "name is empty");
+        ensure!(
+            self.scene.ends_with(".glb"),
+            "scene {:?} is not a glb file",
+            self.scene
+        );
+        ensure!(
+            !self.enemy_spawns.is_empty(),
+            "environments need at least one enemy spawn"
+        );
+
+        for effect in &self.field_effects {
+            match effect.kind {
+                FieldEffectKind::Empower { multiplier, .. } => ensure!(
+                    multiplier >= 0.0,
+                    "field effect {} has a negative multiplier",
+                    effect.name
+                ),
+                FieldEffectKind::Favour { weight, .. } => ensure!(
+                    weight > 0.0,
+                    "field effect {} needs a positive weight",
+                    effect.name
+                ),
+                FieldEffectKind::Hide { .. } => {}
+            }
+        }
+
+        let (first, last) = self.rounds;
+        ensure!(
+            first >= 1 && first <= last,
+            "rounds ({first}, {last}) is not a valid range"
+        );
+
+        Ok(())
+    }
+
+    pub fn appears_in(&self, round: u32) -> bool {
+        (self.rounds.0..=self.rounds.1).contains(&round)
+    }
+
+    pub fn field(&self) -> Field {
+        Field {
+            effects: self.field_effects.clone(),
+        }
+    }
+
+    pub fn scene_handle(&self) -> Handle<Scene> {
+        let path = format!("{}#Scene0", self.scene);
+
+        Handle::weak(HandleId::AssetPathId(path.as_str().into()))
+    }
+
+    pub fn gltf_handle(&self) -> Handle<Gltf> {
+        Handle::weak(HandleId::AssetPathId(self.scene.as_str().into()))
+    }
+
+    pub fn layout(&self, gltfs: &Assets<Gltf>, nodes: &Assets<GltfNode>) -> EnvironmentLayout {
+        let gltf = gltfs.get(&self.gltf_handle());
+        let node = |name: &str| {
+            gltf.and_then(|x| x.named_nodes.get(name))
+                .and_then(|x| nodes.get(x))
+                .map(|x| x.transform)
+        };
+
+        let camera = node("Camera").unwrap_or_else(|| {
+            Transform::from_translation(self.camera.translation)
+                .looking_at(self.camera.looking_at, Vec3::Y)
+        });
+
+        let light = node("Light").unwrap_or_else(|| {
+            Transform::from_translation(self.light.translation).with_rotation(
+                Quat::from_rotation_x(-45_f32.to_radians())
+                    * Quat::from_rotation_y(-45_f32.to_radians()),
+            )
+        });
+
+        let mut spawn_names: Vec<_> = gltf
+            .into_iter()
+            .flat_map(|x| x.named_nodes.keys())
+            .filter(|x| x.starts_with("EnemySpawn"))
+            .collect();
+        spawn_names.sort();
+
+        let mut enemy_spawns: Vec<_> = spawn_names
+            .into_iter()
+            .filter_map(|x| node(x.as_str()))
+            .collect();
+        if enemy_spawns.is_empty() {
+            enemy_spawns = self
+                .enemy_spawns
+                .iter()
+                .map(|x| Transform::from_translation(*x))
+                .collect();
+        }
+
+        let (r, g, b) = self.light.color;
+
+        EnvironmentLayout {
+            scene: self.scene_handle(),
+            camera,
+            light,
+            point_light: PointLight {
+                color: Color::rgb(r, g, b),
+                shadows_enabled: true,
+                range: self.light.range,
+                intensity: self.light.intensity,
+                shadow_depth_bias: 0.001,
+                ..default()
+            },
+            enemy_spawns,
+        }
+    }
+}
+
+#[derive(Debug, TypeUuid)]
+#[uuid = "b4f3c7a2-1e6d-4f0b-8c39-7d2a5e9f1c68"]
+pub struct EnvironmentList {
+    pub environments: Vec<EnvironmentDefinition>,
+}
+
+#[derive(Default)]
+struct EnvironmentListLoader;
+
+impl AssetLoader for EnvironmentListLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let definitions: Vec<EnvironmentDefinition> = ron::de::from_bytes(bytes)?;
+
+            let mut environments = Vec::new();
+            for definition in definitions {
+                match definition.validate() {
+                    Ok(()) => environments.push(definition),
+                    Err(error) => error!("invalid environment {}: {error}", definition.name),
+                }
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(EnvironmentList { environments }));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["environments.ron"]
+    }
+}
+
+/// The environments battles can happen in, available once [`Environments::is_ready`] is true.
+pub struct Environments {
+    list: Handle<EnvironmentList>,
+    definitions: Vec<EnvironmentDefinition>,
+    ready: bool,
+}
+
+impl Environments {
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// Picks a random environment for the round, preferring ones of the given biome.
+    pub fn random(&self, round: u32, biome: Option<&str>) -> &EnvironmentDefinition {
+        let in_round: Vec<_> = self
+            .definitions
+            .iter()
+            .filter(|x| x.appears_in(round))
+            .collect();
+
+        let in_biome: Vec<_> = in_round
+            .iter()
+            .copied()
+            .filter(|x| biome.is_some() && x.biome.as_deref() == biome)
+            .collect();
+
+        if !in_biome.is_empty() {
+            in_biome[fastrand::usize(..in_biome.len())]
+        } else if !in_round.is_empty() {
+            in_round[fastrand::usize(..in_round.len())]
+        } else {
+            &self.definitions[fastrand::usize(..self.definitions.len())]
+        }
+    }
+}
+
+fn load_environment_list(
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<Loading>,
+    mut commands: Commands,
+) {
+    let list = asset_server.load(LIST_PATH);
+
+    loading.assets.push(list.clone_untyped());
+
+    commands.insert_resource(Environments {
+        list,
+        definitions: vec![],
+        ready: false,
+    });
+}
+
+fn read_environment_list(
+    mut environments: ResMut<Environments>,
+    lists: Res<Assets<EnvironmentList>>,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<Loading>,
+    mut reported: Local<bool>,
+) {
+    if environments.ready || *reported {
+        return;
+    }
+
+    // battles need somewhere to happen, so the game can't leave the load screen
+    if let Some(list) = lists.get(&environments.list) {
+        if list.environments.is_empty() {
+            error!("no valid environments were found");
+            loading
+                .errors
+                .push(format!("No valid environments were found in {LIST_PATH}"));
+            *reported = true;
+
+            return;
+        }
+
+        // the whole gltf is needed for its named nodes, not only the scene
+        loading.assets.extend(
+            list.environments
+                .iter()
+                .map(|x| asset_server.load_untyped(&x.scene)),
+        );
+
+        environments.definitions = list.environments.clone();
+        environments.ready = true;
+    } else if asset_server.get_load_state(&environments.list) == LoadState::Failed {
+        error!("failed to load the environment list");
+        loading
+            .errors
+            .push(format!("The environment list {LIST_PATH} failed to load"));
+        *reported = true;
+    }
+}
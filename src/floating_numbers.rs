@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, render::view::RenderLayers, utils::HashMap};
+use bevy_tweening::{
+    lens::{TextColorLens, TransformPositionLens},
+    Animator, Delay, EaseFunction, Tween, TweeningType,
+};
+
+use crate::{
+    battle::{BattleCamera, Enemy, PlayerHealthBar, CARDS_LAYER, SHIELD_COLOR},
+    board::Element,
+    combat::{CombatTarget, DamageDealt, Healed, Shielded},
+    utils::{project_between_cameras, DelayedDespawn},
+};
+
+pub struct FloatingNumbersPlugin;
+
+impl Plugin for FloatingNumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_floating_numbers);
+    }
+}
+
+const FONT_SIZE: f32 = 220.0;
+const RISE: f32 = 350.0;
+const FADE_IN_TIME: f32 = 0.1;
+const LIFETIME: f32 = 1.2;
+const STAGGER_DELAY: f32 = 0.15;
+const STAGGER_OFFSET: Vec2 = Vec2::new(120.0, 80.0);
+
+struct FloatingNumber {
+    target: CombatTarget,
+    text: String,
+    color: Color,
+    scale: f32,
+}
+
+impl FloatingNumber {
+    fn damage(event: &DamageDealt) -> Self {
+        Self {
+            target: event.target,
+            text: if event.amount == 0 && event.absorbed != 0 {
+                "Blocked".into()
+            } else {
+                event.amount.to_string()
+            },
+            color: event.element.map(|x| x.color()).unwrap_or(Color::WHITE),
+            scale: Self::scale(event.combo, event.critical),
+        }
+    }
+
+    fn heal(event: &Healed) -> Self {
+        Self {
+            target: event.target,
+            text: format!("+{}", event.amount),
+            color: Element::Heal.color(),
+            scale: Self::scale(event.combo, event.critical),
+        }
+    }
+
+    fn shield(event: &Shielded) -> Self {
+        Self {
+            target: event.target,
+            text: format!("+{}", event.amount),
+            color: SHIELD_COLOR,
+            scale: Self::scale(event.combo, false),
+        }
+    }
+
+    fn scale(combo: u32, critical: bool) -> f32 {
+        let combo_scale = 1.0 + 0.15 * combo.saturating_sub(1).min(4) as f32;
+
+        if critical {
+            combo_scale * 1.5
+        } else {
+            combo_scale
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_floating_numbers(
+    mut damage_events: EventReader<DamageDealt>,
+    mut heal_events: EventReader<Healed>,
+    mut shield_events: EventReader<Shielded>,
+    enemies: Query<&Enemy>,
+    player_health_bars: Query<Entity, With<PlayerHealthBar>>,
+    anchors: Query<(&GlobalTransform, &RenderLayers)>,
+    cameras: Query<(&Camera, &GlobalTransform, &RenderLayers), With<BattleCamera>>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let numbers: Vec<_> = damage_events
+        .iter()
+        .map(FloatingNumber::damage)
+        .chain(heal_events.iter().map(FloatingNumber::heal))
+        .chain(shield_events.iter().map(FloatingNumber::shield))
+        .collect();
+
+    if numbers.is_empty() {
+        return;
+    }
+
+    let cards_camera = cameras
+        .iter()
+        .find(|(_, _, layers)| **layers == CARDS_LAYER)
+        .map(|(camera, transform, _)| (camera, transform));
+
+    let cards_camera = match cards_camera {
+        Some(camera) => camera,
+        None => return,
+    };
+
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    let mut stagger: HashMap<CombatTarget, usize> = default();
+
+    for number in numbers {
+        let anchor = match number.target {
+            CombatTarget::Player => player_health_bars.iter().next(),
+            CombatTarget::Enemy(entity) => enemies.get(entity).ok().map(|x| x.health_bar()),
+        };
+
+        let position =
+            anchor
+                .and_then(|anchor| anchors.get(anchor).ok())
+                .and_then(|(transform, layers)| {
+                    let (camera, camera_transform, _) = cameras
+                        .iter()
+                        .find(|(_, _, camera_layers)| camera_layers.intersects(layers))?;
+
+                    project_between_cameras(
+                        (camera, camera_transform),
+                        cards_camera,
+                        transform.translation(),
+                    )
+                });
+
+        let position = match position {
+            Some(position) => position,
+            None => continue,
+        };
+
+        let index = stagger.entry(number.target).or_default();
+        let side = if *index % 2 == 0 { 1.0 } else { -1.0 };
+        let offset = STAGGER_OFFSET * Vec2::new(side * ((*index + 1) / 2) as f32, *index as f32);
+        let delay = Duration::from_secs_f32(STAGGER_DELAY * *index as f32);
+        *index += 1;
+
+        let start = (position + offset).extend(100.0);
+        let end = start + Vec3::Y * RISE;
+
+        let transparent = number.color * Vec4::new(1.0, 1.0, 1.0, 0.0);
+
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::from_section(
+                    number.text,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: FONT_SIZE * number.scale,
+                        color: transparent,
+                    },
+                )
+                .with_alignment(TextAlignment::CENTER),
+                transform: Transform::from_translation(start),
+                ..default()
+            })
+            .insert(CARDS_LAYER)
+            .insert(DelayedDespawn::new(
+                delay + Duration::from_secs_f32(FADE_IN_TIME + LIFETIME),
+            ))
+            .insert(Animator::new(Delay::new(delay).then(Tween::new(
+                EaseFunction::QuadraticOut,
+                TweeningType::Once,
+                Duration::from_secs_f32(FADE_IN_TIME + LIFETIME),
+                TransformPositionLens { start, end },
+            ))))
+            .insert(Animator::new(
+                Delay::new(delay)
+                    .then(Tween::new(
+                        EaseFunction::QuadraticOut,
+                        TweeningType::Once,
+                        Duration::from_secs_f32(FADE_IN_TIME),
+                        TextColorLens {
+                            start: transparent,
+                            end: number.color,
+                            section: 0,
+                        },
+                    ))
+                    .then(Tween::new(
+                        EaseFunction::QuadraticIn,
+                        TweeningType::Once,
+                        Duration::from_secs_f32(LIFETIME),
+                        TextColorLens {
+                            start: number.color,
+                            end: transparent,
+                            section: 0,
+                        },
+                    )),
+            ));
+    }
+}
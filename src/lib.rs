@@ -3,9 +3,20 @@ use bevy::prelude::*;
 use bevy_tweening::TweeningPlugin;
 use board::{BoardPlugin, BoardState};
 use cards::{CardPlugin, CardsState};
+use combat::CombatPlugin;
+use enemies::EnemiesPlugin;
+use environments::EnvironmentsPlugin;
+use floating_numbers::FloatingNumbersPlugin;
 use iyes_loopless::prelude::*;
 use main_state::{MainState, MainStatePlugin};
+use map::MapPlugin;
 use particles::ParticlesPlugin;
+use recipes::RecipesPlugin;
+use rewards::RewardsPlugin;
+use run_events::RunEventsPlugin;
+use save::SavePlugin;
+use services::ServicesPlugin;
+use shop::ShopPlugin;
 use std::{fmt::Debug, hash::Hash};
 use transitions::TransitionPlugin;
 use utils::UtilsPlugin;
@@ -16,10 +27,23 @@ mod wasm;
 mod battle;
 mod board;
 mod cards;
+mod combat;
+mod enemies;
+mod environments;
+mod floating_numbers;
 mod main_state;
+mod map;
 mod particles;
 mod player;
 mod prefab;
+mod recipes;
+mod rewards;
+mod run_config;
+mod run_events;
+mod save;
+mod services;
+mod shop;
+pub mod sim;
 mod transitions;
 mod tween_untils;
 mod ui;
@@ -50,10 +74,21 @@ pub fn build_app() -> App {
     .add_plugin(BoardPlugin)
     .add_plugin(UtilsPlugin)
     .add_plugin(CardPlugin)
+    .add_plugin(RecipesPlugin)
+    .add_plugin(CombatPlugin)
+    .add_plugin(EnemiesPlugin)
+    .add_plugin(EnvironmentsPlugin)
     .add_plugin(BattlePlugin)
     .add_plugin(TransitionPlugin)
     .add_plugin(MainStatePlugin)
+    .add_plugin(RewardsPlugin)
+    .add_plugin(MapPlugin)
+    .add_plugin(ServicesPlugin)
+    .add_plugin(ShopPlugin)
+    .add_plugin(RunEventsPlugin)
+    .add_plugin(SavePlugin)
     .add_plugin(ParticlesPlugin)
+    .add_plugin(FloatingNumbersPlugin)
     .add_system(log_states::<BoardState>)
     .add_system(log_states::<BattleState>)
     .add_system(log_states::<MainState>)
@@ -1,13 +1,25 @@
 use std::time::Duration;
 
-use bevy::{asset::LoadState, prelude::*};
+use bevy::{
+    asset::LoadState,
+    gltf::{Gltf, GltfNode},
+    prelude::*,
+};
 use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
 
 use crate::{
-    battle::{BattleCleanedUp, BattlePrefab, BattleResources, BattleState, EnemyKind, EnemyPrefab},
+    battle::{BattleCleanedUp, BattlePrefab, BattleResources, BattleState, EnemyPrefab},
+    board::Element,
     cards::CardsState,
-    player::Player,
+    combat::{CombatStats, FieldEffect, FieldEffectKind},
+    enemies::{Enemies, EnemyDefinition},
+    environments::Environments,
+    map::{NodeKind, RunMap},
+    player::{Evolution, Player},
     prefab::*,
+    run_config::RunConfig,
     transitions::{FadeScreenPrefab, Transition, TransitionDirection, TransitionEnd},
     ui::*,
     utils::Loading,
@@ -20,25 +32,23 @@ impl Plugin for MainStatePlugin {
         app.add_plugin(OnClickPlugin::<Restart>::new())
             .add_loopless_state(MainState::Load)
             .insert_resource(Player::default())
+            .insert_resource(Evolution::default())
             .insert_resource(Difficulty::default())
+            .insert_resource(RunConfig::default())
             .add_startup_system(load_assets)
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(MainState::Load)
                     .with_system(loaded)
+                    .with_system(show_load_errors)
                     .into(),
             )
-            .add_system_set(
-                ConditionSet::new()
-                    .run_in_state(MainState::Map)
-                    .with_system(start_battle)
-                    .into(),
-            )
+            .add_enter_system(MainState::Battle, start_battle)
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(MainState::Battle)
                     .with_system(die)
-                    .with_system(go_to_map.run_on_event::<BattleCleanedUp>())
+                    .with_system(go_to_reward.run_on_event::<BattleCleanedUp>())
                     .into(),
             )
             .add_enter_system(BattleState::End, BattleResources::clean_up_system)
@@ -63,10 +73,12 @@ impl Plugin for MainStatePlugin {
                     .with_system(clean_up_battle)
                     .with_system(reset_player.run_on_event::<TransitionEnd>())
                     .with_system(reset_difficulty.run_on_event::<TransitionEnd>())
+                    .with_system(reset_run_config.run_on_event::<TransitionEnd>())
+                    .with_system(reset_stats.run_on_event::<TransitionEnd>())
                     .with_system(clean_up_death_screen.run_on_event::<TransitionEnd>())
                     .with_system(clean_up_win_screen.run_on_event::<TransitionEnd>())
                     .with_system(Transition::clean_up_system.run_on_event::<BattleCleanedUp>())
-                    .with_system(go_to_map.run_on_event::<BattleCleanedUp>())
+                    .with_system(go_to_menu.run_on_event::<BattleCleanedUp>())
                     .into(),
             );
     }
@@ -75,8 +87,14 @@ impl Plugin for MainStatePlugin {
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum MainState {
     Load,
+    /// Picks between a new run and continuing the saved one.
+    Menu,
     Map,
     Battle,
+    Reward,
+    Service,
+    Shop,
+    Event,
     Death,
     Win,
     Restart,
@@ -85,62 +103,304 @@ pub enum MainState {
 #[derive(Clone, Copy)]
 struct Restart;
 
-struct Difficulty {
-    round: u32,
-    enemy_health: u32,
-    enemy_attack: u32,
+const ELITE_HEALTH_MULTIPLIER: f32 = 1.4;
+const ELITE_ATTACK_BONUS: u32 = 2;
+
+/// How fast enemies get stronger over the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter, Serialize, Deserialize)]
+pub enum DifficultyLevel {
+    Easy,
+    Normal,
+    Hard,
 }
 
-impl Default for Difficulty {
-    fn default() -> Self {
+impl DifficultyLevel {
+    /// The enemy health multiplier of the first round and how much it grows every round.
+    fn health_multiplier(&self) -> (f32, f32) {
+        match self {
+            DifficultyLevel::Easy => (0.85, 1.15),
+            DifficultyLevel::Normal => (1.0, 1.2),
+            DifficultyLevel::Hard => (1.15, 1.25),
+        }
+    }
+
+    fn attack_per_round(&self) -> u32 {
+        match self {
+            DifficultyLevel::Easy => 1,
+            DifficultyLevel::Normal => 2,
+            DifficultyLevel::Hard => 3,
+        }
+    }
+}
+
+/// Makes a run harder on top of the level, any number of them can be picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
+pub enum Modifier {
+    EnemyHealth,
+    EnemyAttack,
+    RareHeals,
+    SmallerHand,
+    ShorterTimer,
+}
+
+pub const MODIFIER_ENEMY_HEALTH: f32 = 1.25;
+pub const MODIFIER_ENEMY_ATTACK: u32 = 2;
+/// How often heal gems fall compared to the others.
+pub const MODIFIER_HEAL_WEIGHT: f32 = 0.5;
+pub const MODIFIER_MOVE_TIME: f32 = 1.5;
+
+impl Modifier {
+    pub fn description(&self) -> String {
+        match self {
+            Modifier::EnemyHealth => format!(
+                "Enemies have {}% more health",
+                ((MODIFIER_ENEMY_HEALTH - 1.0) * 100.0).round()
+            ),
+            Modifier::EnemyAttack => format!("Enemies attack for {MODIFIER_ENEMY_ATTACK} more"),
+            Modifier::RareHeals => "Heal gems are rarer".into(),
+            Modifier::SmallerHand => "Draw one card less".into(),
+            Modifier::ShorterTimer => format!("{MODIFIER_MOVE_TIME} seconds less to move"),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Difficulty {
+    pub round: u32,
+    pub level: DifficultyLevel,
+    pub modifiers: Vec<Modifier>,
+    enemy_health_multiplier: f32,
+    /// Grows by fractions of a point in long runs, rounded down when used.
+    enemy_attack_bonus: f32,
+}
+
+impl Difficulty {
+    pub fn new(level: DifficultyLevel, modifiers: Vec<Modifier>) -> Self {
         Self {
             round: 1,
-            enemy_health: 40,
-            enemy_attack: 10,
+            level,
+            modifiers,
+            enemy_health_multiplier: level.health_multiplier().0,
+            enemy_attack_bonus: 0.0,
+        }
+    }
+
+    pub fn has(&self, modifier: Modifier) -> bool {
+        self.modifiers.contains(&modifier)
+    }
+
+    /// Picks the modifier if it wasn't picked yet, takes it out otherwise.
+    pub fn toggle(&mut self, modifier: Modifier) {
+        match self.modifiers.iter().position(|&x| x == modifier) {
+            Some(i) => {
+                self.modifiers.remove(i);
+            }
+            None => self.modifiers.push(modifier),
+        }
+    }
+
+    /// The enemy's max health and attack for the current round.
+    pub fn enemy_stats(&self, definition: &EnemyDefinition) -> (u32, u32) {
+        let mut health_multiplier = self.enemy_health_multiplier;
+        let mut attack = definition.attack + self.enemy_attack_bonus as u32;
+
+        if self.has(Modifier::EnemyHealth) {
+            health_multiplier *= MODIFIER_ENEMY_HEALTH;
+        }
+        if self.has(Modifier::EnemyAttack) {
+            attack += MODIFIER_ENEMY_ATTACK;
+        }
+
+        (
+            (definition.health as f32 * health_multiplier) as u32,
+            attack,
+        )
+    }
+
+    /// Cards drawn less than the hand size every turn.
+    pub fn hand_size_penalty(&self) -> usize {
+        self.has(Modifier::SmallerHand) as usize
+    }
+
+    /// Seconds taken off the time to move every turn.
+    pub fn move_time_penalty(&self) -> f32 {
+        if self.has(Modifier::ShorterTimer) {
+            MODIFIER_MOVE_TIME
+        } else {
+            0.0
+        }
+    }
+
+    /// Added to the field effects of every battle.
+    pub fn field_effects(&self) -> Vec<FieldEffect> {
+        let mut effects = vec![];
+
+        if self.has(Modifier::RareHeals) {
+            effects.push(FieldEffect {
+                name: "Drought".into(),
+                kind: FieldEffectKind::Favour {
+                    element: Element::Heal,
+                    weight: MODIFIER_HEAL_WEIGHT,
+                },
+            });
+        }
+
+        effects
+    }
+
+    /// The level followed by how many modifiers are on.
+    pub fn label(&self) -> String {
+        match self.modifiers.len() {
+            0 => self.level.to_string(),
+            count => format!("{} +{count}", self.level),
         }
     }
+
+    /// Makes enemies stronger by as many rounds of a standard run as `pace`, see
+    /// [`RunConfig::pace`].
+    pub fn next_round(&mut self, pace: f32) {
+        self.enemy_health_multiplier *= self.level.health_multiplier().1.powf(pace);
+        self.enemy_attack_bonus += self.level.attack_per_round() as f32 * pace;
+
+        self.round += 1;
+    }
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self::new(DifficultyLevel::Normal, vec![])
+    }
 }
 
 fn load_assets(asset_server: Res<AssetServer>, mut loading: ResMut<Loading>) {
-    loading.assets.extend([
-        asset_server.load_untyped("scenes/battles/super_basic.glb"),
-        asset_server.load_untyped("fonts/FiraMono-Medium.ttf"),
-    ]);
+    loading
+        .assets
+        .push(asset_server.load_untyped("fonts/FiraMono-Medium.ttf"));
 }
 
-fn loaded(asset_server: Res<AssetServer>, loading: Res<Loading>, mut commands: Commands) {
+fn loaded(
+    asset_server: Res<AssetServer>,
+    loading: Res<Loading>,
+    enemies: Res<Enemies>,
+    environments: Res<Environments>,
+    mut commands: Commands,
+) {
+    // models and scenes are only added to loading once their definitions are read
+    if !enemies.is_ready() || !environments.is_ready() {
+        return;
+    }
+
     match asset_server.get_group_load_state(loading.assets.iter().map(|x| x.id)) {
         LoadState::NotLoaded | LoadState::Loading => {}
-        _ => commands.insert_resource(NextState(MainState::Map)),
+        _ => commands.insert_resource(NextState(MainState::Menu)),
+    }
+}
+
+#[derive(Component)]
+struct LoadErrorScreen;
+
+/// Lists what keeps the game from starting, see [`Loading::errors`].
+fn show_load_errors(
+    loading: Res<Loading>,
+    screens: Query<Entity, With<LoadErrorScreen>>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    if !loading.is_changed() || loading.errors.is_empty() {
+        return;
+    }
+
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let mut children: Vec<Child> = vec![TextPrefab {
+        text: "The game can't start".into(),
+        size: 60.0,
+        color: Color::WHITE,
+        font: font.clone(),
     }
+    .into()];
+
+    children.extend(loading.errors.iter().map(|error| {
+        Child::from(TextPrefab {
+            text: error.clone(),
+            size: 30.0,
+            color: Color::ORANGE_RED,
+            font: font.clone(),
+        })
+    }));
+
+    commands
+        .spawn_prefab(FullScreen {
+            color: Color::BLACK,
+            child: VBox {
+                gap: 20.0,
+                children,
+            },
+        })
+        .insert(LoadErrorScreen);
+
+    commands
+        .spawn_bundle(Camera3dBundle::default())
+        .insert(LoadErrorScreen);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn start_battle(
     mut difficulty: ResMut<Difficulty>,
     mut commands: Commands,
-    player: Res<Player>,
+    mut player: ResMut<Player>,
+    map: Res<RunMap>,
+    config: Res<RunConfig>,
+    enemies: Res<Enemies>,
+    environments: Res<Environments>,
+    gltfs: Res<Assets<Gltf>>,
+    nodes: Res<Assets<GltfNode>>,
     asset_server: Res<AssetServer>,
 ) {
+    player.start_battle();
+
+    let kind = map.current().map_or(NodeKind::Battle, |x| x.kind);
+    let enemy_round = config.enemy_round(difficulty.round);
+
+    let definition = if kind == NodeKind::Boss {
+        enemies.random_boss(enemy_round)
+    } else {
+        enemies.random(enemy_round)
+    }
+    .clone();
+
+    // acts without a boss of their own end on an enemy as strong as an elite
+    let (mut max_health, mut attack) = difficulty.enemy_stats(&definition);
+    if kind == NodeKind::Elite || (kind == NodeKind::Boss && definition.boss.is_none()) {
+        max_health = (max_health as f32 * ELITE_HEALTH_MULTIPLIER) as u32;
+        attack += ELITE_ATTACK_BONUS;
+    }
+
+    let environment = environments.random(enemy_round, definition.biome.as_deref());
+    let mut field = environment.field();
+    field.effects.extend(difficulty.field_effects());
+    let environment = environment.layout(&gltfs, &nodes);
+
     commands.spawn_prefab(BattlePrefab {
         round: difficulty.round,
-        num_rounds: 8,
-        environment: asset_server.load("scenes/battles/super_basic.glb#Scene0"),
+        num_rounds: config.rounds,
         enemy: EnemyPrefab {
-            kind: EnemyKind::random(),
-            max_health: difficulty.enemy_health,
-            attack: difficulty.enemy_attack,
-            transform: default(),
+            max_health,
+            attack,
+            definition,
+            transform: environment.enemy_spawns[0],
         },
+        environment,
+        field,
         spells: player.spells.clone(),
         font: asset_server.load("fonts/FiraMono-Medium.ttf"),
     });
 
-    difficulty.enemy_health = (difficulty.enemy_health as f32 * 1.2) as u32;
-    difficulty.enemy_attack += 2;
+    difficulty.next_round(config.pace());
 
-    difficulty.round += 1;
-
-    commands.insert_resource(NextState(MainState::Battle));
     commands.insert_resource(NextState(BattleState::Intro));
 }
 
@@ -152,20 +412,48 @@ fn die(player: Res<Player>, mut commands: Commands) {
     }
 }
 
-fn go_to_map(mut commands: Commands, difficulty: Res<Difficulty>) {
-    if difficulty.round > 8 {
+fn go_to_menu(mut commands: Commands, difficulty: Res<Difficulty>, config: Res<RunConfig>) {
+    if difficulty.round > config.rounds {
+        commands.insert_resource(NextState(MainState::Win))
+    } else {
+        // the difficulty of the new run is picked on the menu
+        commands.insert_resource(NextState(MainState::Menu))
+    }
+}
+
+fn go_to_reward(mut commands: Commands, difficulty: Res<Difficulty>, config: Res<RunConfig>) {
+    if difficulty.round > config.rounds {
         commands.insert_resource(NextState(MainState::Win))
     } else {
-        commands.insert_resource(NextState(MainState::Map))
+        commands.insert_resource(NextState(MainState::Reward))
     }
 }
 
 #[derive(Component)]
 struct DeathScreen;
 
-fn show_death_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn show_death_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    stats: Res<CombatStats>,
+    difficulty: Res<Difficulty>,
+) {
     let font = asset_server.load("fonts/FiraMono-Medium.ttf");
 
+    let mut children = stats_text(&stats, &difficulty, &font);
+    children.push(
+        ButtonPrefab {
+            on_click: Restart,
+            child: TextPrefab {
+                text: "Restart".into(),
+                size: 40.0,
+                color: Color::BLACK,
+                font,
+            },
+        }
+        .into(),
+    );
+
     commands
         .spawn_prefab(FullScreen {
             color: Color::Rgba {
@@ -174,19 +462,35 @@ fn show_death_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
                 blue: 0.0,
                 alpha: 0.5,
             },
-            child: ButtonPrefab {
-                on_click: Restart,
-                child: TextPrefab {
-                    text: "Restart".into(),
-                    size: 40.0,
-                    color: Color::BLACK,
-                    font,
-                },
+            child: VBox {
+                gap: 30.0,
+                children,
             },
         })
         .insert(DeathScreen);
 }
 
+fn stats_text(stats: &CombatStats, difficulty: &Difficulty, font: &Handle<Font>) -> Vec<Child> {
+    [
+        format!("Difficulty: {}", difficulty.label()),
+        format!("Damage dealt: {}", stats.damage_dealt),
+        format!("Damage taken: {}", stats.damage_taken),
+        format!("Healed: {}", stats.healed),
+        format!("Damage blocked: {}", stats.damage_blocked),
+        format!("Enemies defeated: {}", stats.enemies_killed),
+    ]
+    .into_iter()
+    .map(|text| {
+        Child::from(TextPrefab {
+            text,
+            size: 30.0,
+            color: Color::WHITE,
+            font: font.clone(),
+        })
+    })
+    .collect()
+}
+
 fn clean_up_death_screen(screens: Query<Entity, With<DeathScreen>>, mut commands: Commands) {
     for entity in &screens {
         commands.entity(entity).despawn_recursive();
@@ -196,8 +500,35 @@ fn clean_up_death_screen(screens: Query<Entity, With<DeathScreen>>, mut commands
 #[derive(Component)]
 struct WinScreen;
 
-fn show_win_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn show_win_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    stats: Res<CombatStats>,
+    difficulty: Res<Difficulty>,
+) {
     let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    let mut children: Vec<Child> = vec![TextPrefab {
+        text: "You Won!".into(),
+        size: 80.0,
+        color: Color::WHITE,
+        font: font.clone(),
+    }
+    .into()];
+    children.extend(stats_text(&stats, &difficulty, &font));
+    children.push(
+        ButtonPrefab {
+            on_click: Restart,
+            child: TextPrefab {
+                text: "Restart".into(),
+                size: 40.0,
+                color: Color::BLACK,
+                font,
+            },
+        }
+        .into(),
+    );
+
     commands
         .spawn_prefab(FullScreen {
             color: Color::Rgba {
@@ -208,25 +539,7 @@ fn show_win_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
             },
             child: VBox {
                 gap: 30.0,
-                children: vec![
-                    TextPrefab {
-                        text: "You Won!".into(),
-                        size: 80.0,
-                        color: Color::WHITE,
-                        font: font.clone(),
-                    }
-                    .into(),
-                    ButtonPrefab {
-                        on_click: Restart,
-                        child: TextPrefab {
-                            text: "Restart".into(),
-                            size: 40.0,
-                            color: Color::BLACK,
-                            font,
-                        },
-                    }
-                    .into(),
-                ],
+                children,
             },
         })
         .insert(WinScreen);
@@ -274,3 +587,11 @@ fn reset_player(mut player: ResMut<Player>) {
 fn reset_difficulty(mut difficulty: ResMut<Difficulty>) {
     *difficulty = default();
 }
+
+fn reset_run_config(mut config: ResMut<RunConfig>) {
+    *config = default();
+}
+
+fn reset_stats(mut stats: ResMut<CombatStats>) {
+    *stats = default();
+}
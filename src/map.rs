@@ -0,0 +1,599 @@
+use std::ops::Range;
+
+use bevy::{
+    input::{mouse::MouseButtonInput, ButtonState},
+    prelude::*,
+    render::camera::ScalingMode,
+};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    main_state::MainState,
+    player::Player,
+    prefab::*,
+    run_config::RunConfig,
+    services::Service,
+    transitions::TransitionEnd,
+    ui::*,
+    utils::{WorldCursor, WorldHover},
+};
+
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(OnClickPlugin::<CloseDeck>::new())
+            .init_resource::<RunMap>()
+            .add_enter_system(MainState::Map, show_map_screen)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(MainState::Map)
+                    .with_system(hover_map_nodes)
+                    .with_system(click_map)
+                    .with_system(close_deck)
+                    .into(),
+            )
+            .add_system(
+                reset_map
+                    .run_in_state(MainState::Restart)
+                    .run_on_event::<TransitionEnd>(),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Battle,
+    /// A stronger enemy that pays more gold.
+    Elite,
+    /// Heals, then lets a card be upgraded.
+    Rest,
+    Shop,
+    Event,
+    /// The last fight of the run.
+    Boss,
+}
+
+/// Health restored at a rest, as a fraction of the max health.
+const REST_HEAL: f32 = 0.25;
+
+impl NodeKind {
+    fn name(&self) -> &'static str {
+        match self {
+            NodeKind::Battle => "Battle",
+            NodeKind::Elite => "Elite",
+            NodeKind::Rest => "Rest",
+            NodeKind::Shop => "Shop",
+            NodeKind::Event => "Event",
+            NodeKind::Boss => "Boss",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            NodeKind::Battle => Color::rgb(0.6, 0.6, 0.6),
+            NodeKind::Elite => Color::rgb(0.8, 0.4, 0.1),
+            NodeKind::Rest => Color::rgb(0.2, 0.7, 0.3),
+            NodeKind::Shop => Color::rgb(0.9, 0.75, 0.2),
+            NodeKind::Event => Color::rgb(0.3, 0.5, 0.9),
+            NodeKind::Boss => Color::rgb(0.8, 0.1, 0.1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapNode {
+    pub kind: NodeKind,
+    /// Indices of the nodes of the next layer it leads to.
+    pub next: Vec<usize>,
+}
+
+const ELITE_CHANCE: f32 = 0.25;
+const MIN_LAYER_NODES: usize = 2;
+const MAX_LAYER_NODES: usize = 4;
+/// Chance of a node leading to one more node than the one right above it.
+const BRANCH_CHANCE: f32 = 0.4;
+
+/// A branching graph of nodes, from the first fight up to the last boss. Every path goes through
+/// one fight per round, so the difficulty keeps rising with each layer of fights.
+pub struct RunMap {
+    /// Generates the same layers again, which is all saves keep of them.
+    pub seed: u64,
+    pub layers: Vec<Vec<MapNode>>,
+    /// Layer and index of the node visited last.
+    pub position: Option<(usize, usize)>,
+}
+
+impl Default for RunMap {
+    fn default() -> Self {
+        Self::generate(fastrand::u64(..), &RunConfig::default())
+    }
+}
+
+impl RunMap {
+    pub fn generate(seed: u64, config: &RunConfig) -> Self {
+        let rng = &fastrand::Rng::with_seed(seed);
+        let mut kinds: Vec<Vec<NodeKind>> = vec![];
+
+        for round in 1..=config.rounds {
+            if config.is_boss_round(round) {
+                kinds.push(vec![NodeKind::Boss]);
+            } else {
+                let count = if round == 1 {
+                    MAX_LAYER_NODES - 1
+                } else {
+                    rng.usize(MIN_LAYER_NODES..=MAX_LAYER_NODES)
+                };
+                kinds.push(
+                    (0..count)
+                        .map(|_| {
+                            if config.is_elite_round(round) && rng.f32() < ELITE_CHANCE {
+                                NodeKind::Elite
+                            } else {
+                                NodeKind::Battle
+                            }
+                        })
+                        .collect(),
+                );
+            }
+
+            if config.is_interlude_round(round) && round < config.rounds {
+                let count = rng.usize(MIN_LAYER_NODES..=MAX_LAYER_NODES);
+                kinds.push(
+                    (0..count)
+                        .map(|_| match rng.usize(..4) {
+                            0 => NodeKind::Shop,
+                            1 => NodeKind::Event,
+                            // rests are the most common, they are what keeps a run going
+                            _ => NodeKind::Rest,
+                        })
+                        .collect(),
+                );
+            }
+        }
+
+        let mut layers: Vec<Vec<MapNode>> = kinds
+            .iter()
+            .map(|layer| {
+                layer
+                    .iter()
+                    .map(|&kind| MapNode { kind, next: vec![] })
+                    .collect()
+            })
+            .collect();
+
+        for i in 0..layers.len() - 1 {
+            let edges = connect_layers(layers[i].len(), layers[i + 1].len(), rng);
+
+            for (from, to) in edges {
+                layers[i][from].next.push(to);
+            }
+        }
+
+        Self {
+            seed,
+            layers,
+            position: None,
+        }
+    }
+
+    pub fn current(&self) -> Option<&MapNode> {
+        self.position.map(|(layer, i)| &self.layers[layer][i])
+    }
+
+    /// The layer the next node is picked from.
+    pub fn next_layer(&self) -> usize {
+        self.position.map_or(0, |(layer, _)| layer + 1)
+    }
+
+    /// Indices of the nodes in the next layer that can be visited.
+    pub fn choices(&self) -> Vec<usize> {
+        match self.current() {
+            Some(node) => node.next.clone(),
+            None => (0..self.layers.first().map_or(0, |x| x.len())).collect(),
+        }
+    }
+
+    pub fn is_visited(&self, layer: usize, index: usize) -> bool {
+        self.position == Some((layer, index))
+    }
+}
+
+/// Where a node sits across its layer, from 0 to 1.
+fn node_offset(index: usize, count: usize) -> f32 {
+    (index as f32 + 0.5) / count as f32
+}
+
+/// Every node leads to the closest node of the next layer, and every node of the next layer
+/// is reached from its closest node, so no paths cross and none are left out.
+fn connect_layers(from: usize, to: usize, rng: &fastrand::Rng) -> Vec<(usize, usize)> {
+    let closest = |offset: f32, count: usize| {
+        (0..count)
+            .min_by(|&a, &b| {
+                let a = (node_offset(a, count) - offset).abs();
+                let b = (node_offset(b, count) - offset).abs();
+                a.total_cmp(&b)
+            })
+            .unwrap()
+    };
+
+    let targets: Vec<_> = (0..from)
+        .map(|i| closest(node_offset(i, from), to))
+        .collect();
+
+    let mut edges: Vec<_> = targets.iter().copied().enumerate().collect();
+
+    for i in 0..to {
+        if !targets.contains(&i) {
+            edges.push((closest(node_offset(i, to), from), i));
+        }
+    }
+
+    // a branch to the right only crosses nothing when the next node goes further right
+    for (i, target) in targets.iter().enumerate() {
+        let right = target + 1;
+        let clear = targets.get(i + 1).map_or(true, |&x| x >= right);
+
+        if right < to && clear && !edges.contains(&(i, right)) && rng.f32() < BRANCH_CHANCE {
+            edges.push((i, right));
+        }
+    }
+
+    edges.sort_unstable();
+    edges
+}
+
+#[derive(Component)]
+struct MapScreen;
+
+#[derive(Component)]
+struct MapNodeButton {
+    layer: usize,
+    index: usize,
+}
+
+#[derive(Component)]
+struct DeckButton;
+
+/// Lists the whole deck over the map.
+#[derive(Component)]
+struct DeckOverlay;
+
+#[derive(Clone, Copy)]
+struct CloseDeck;
+
+const MAP_HEIGHT: f32 = 3400.0;
+const MAP_WIDTH: f32 = 3000.0;
+const NODE_SIZE: Vec2 = Vec2::new(360.0, 170.0);
+const NODE_HOVER_SCALE: f32 = 1.15;
+/// Longer maps only show this many layers, starting from the node visited last.
+const MAX_VISIBLE_LAYERS: usize = 12;
+
+fn visible_layers(map: &RunMap) -> Range<usize> {
+    let count = map.layers.len().min(MAX_VISIBLE_LAYERS);
+    let start = map
+        .position
+        .map_or(0, |(layer, _)| layer)
+        .min(map.layers.len() - count);
+
+    start..start + count
+}
+
+fn node_position(map: &RunMap, layer: usize, index: usize) -> Vec2 {
+    let visible = visible_layers(map);
+    let layers = visible.len().max(2);
+    let count = map.layers[layer].len();
+
+    Vec2::new(
+        (node_offset(index, count) - 0.5) * MAP_WIDTH,
+        (layer - visible.start) as f32 / (layers - 1) as f32 * MAP_HEIGHT - MAP_HEIGHT / 2.0,
+    )
+}
+
+fn show_map_screen(
+    map: Res<RunMap>,
+    player: Res<Player>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let font: Handle<Font> = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let style = TextStyle {
+        font,
+        font_size: 70.0,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn_bundle(Camera2dBundle {
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::FixedVertical(4000.0),
+                ..Camera2dBundle::default().projection
+            },
+            ..default()
+        })
+        .insert(WorldCursor::default())
+        .insert(MapScreen);
+
+    let choices = map.choices();
+    let next_layer = map.next_layer();
+    let visible = visible_layers(&map);
+
+    commands
+        .spawn_bundle(SpatialBundle::default())
+        .insert(MapScreen)
+        .with_children(|p| {
+            for (layer, nodes) in map.layers.iter().enumerate() {
+                if !visible.contains(&layer) {
+                    continue;
+                }
+
+                for (index, node) in nodes.iter().enumerate() {
+                    let start = node_position(&map, layer, index);
+
+                    for &next in node.next.iter().filter(|_| visible.contains(&(layer + 1))) {
+                        let end = node_position(&map, layer + 1, next);
+                        let path = end - start;
+
+                        p.spawn_bundle(SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::DARK_GRAY,
+                                custom_size: Some(Vec2::new(path.length(), 16.0)),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(
+                                ((start + end) / 2.0).extend(0.0),
+                            )
+                            .with_rotation(Quat::from_rotation_z(path.y.atan2(path.x))),
+                            ..default()
+                        });
+                    }
+
+                    let is_choice = layer == next_layer && choices.contains(&index);
+                    let color = if is_choice || map.is_visited(layer, index) {
+                        node.kind.color()
+                    } else {
+                        // nodes that can't be reached any more fade out
+                        node.kind.color() * 0.4
+                    };
+
+                    let mut node_entity = p.spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(NODE_SIZE),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(start.extend(1.0)),
+                        ..default()
+                    });
+                    node_entity.with_children(|p| {
+                        p.spawn_bundle(Text2dBundle {
+                            text: Text::from_section(node.kind.name(), style.clone())
+                                .with_alignment(TextAlignment::CENTER),
+                            transform: Transform::from_xyz(0.0, 0.0, 1.0),
+                            ..default()
+                        });
+                    });
+
+                    if is_choice {
+                        node_entity
+                            .insert(WorldHover::new(NODE_SIZE))
+                            .insert(MapNodeButton { layer, index });
+                    }
+                }
+            }
+
+            p.spawn_bundle(Text2dBundle {
+                text: Text::from_section(
+                    format!(
+                        "Health {}/{}\nGold {}",
+                        player.current_health, player.max_health, player.gold
+                    ),
+                    TextStyle {
+                        color: Color::WHITE,
+                        ..style.clone()
+                    },
+                )
+                .with_alignment(TextAlignment::TOP_LEFT),
+                transform: Transform::from_xyz(-3000.0, 1800.0, 1.0),
+                ..default()
+            });
+
+            p.spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::WHITE,
+                    custom_size: Some(NODE_SIZE * Vec2::new(1.6, 1.0)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(-2600.0, -1700.0, 1.0),
+                ..default()
+            })
+            .insert(WorldHover::new(NODE_SIZE * Vec2::new(1.6, 1.0)))
+            .insert(DeckButton)
+            .with_children(|p| {
+                p.spawn_bundle(Text2dBundle {
+                    text: Text::from_section(
+                        format!("Deck ({})", player.spells.len()),
+                        style.clone(),
+                    )
+                    .with_alignment(TextAlignment::CENTER),
+                    transform: Transform::from_xyz(0.0, 0.0, 1.0),
+                    ..default()
+                });
+            });
+        });
+}
+
+fn hover_map_nodes(mut nodes: Query<(&mut Transform, &WorldHover), Changed<WorldHover>>) {
+    for (mut transform, hover) in &mut nodes {
+        transform.scale = Vec3::splat(if hover.is_cursor_in {
+            NODE_HOVER_SCALE
+        } else {
+            1.0
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn click_map(
+    mut events: EventReader<MouseButtonInput>,
+    nodes: Query<(&MapNodeButton, &WorldHover)>,
+    deck_buttons: Query<&WorldHover, With<DeckButton>>,
+    overlays: Query<(), With<DeckOverlay>>,
+    screens: Query<Entity, With<MapScreen>>,
+    mut map: ResMut<RunMap>,
+    mut player: ResMut<Player>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let clicked = events
+        .iter()
+        .any(|e| e.state == ButtonState::Pressed && e.button == MouseButton::Left);
+
+    // the deck list covers the map and has its own button to close it
+    if !clicked || !overlays.is_empty() {
+        return;
+    }
+
+    if deck_buttons.iter().any(|x| x.is_cursor_in) {
+        let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+        commands
+            .spawn_prefab(FullScreen {
+                color: Color::BLACK,
+                child: VBox {
+                    gap: 10.0,
+                    children: deck_children(&player, font),
+                },
+            })
+            .insert(DeckOverlay);
+        return;
+    }
+
+    let (layer, index) = match nodes.iter().find(|(_, hover)| hover.is_cursor_in) {
+        Some((node, _)) => (node.layer, node.index),
+        None => return,
+    };
+
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    map.position = Some((layer, index));
+    visit_node(map.layers[layer][index].kind, &mut player, &mut commands);
+}
+
+fn deck_children(player: &Player, font: Handle<Font>) -> Vec<Child> {
+    let mut children: Vec<Child> = vec![TextPrefab {
+        text: format!("Deck ({} cards)", player.spells.len()),
+        size: 50.0,
+        color: Color::WHITE,
+        font: font.clone(),
+    }
+    .into()];
+
+    children.extend(player.spells.iter().map(|spell| {
+        Child::from(TextPrefab {
+            text: spell.description(),
+            size: 24.0,
+            color: Color::WHITE,
+            font: font.clone(),
+        })
+    }));
+
+    children.push(
+        ButtonPrefab {
+            on_click: CloseDeck,
+            child: TextPrefab {
+                text: "Back".into(),
+                size: 30.0,
+                color: Color::BLACK,
+                font,
+            },
+        }
+        .into(),
+    );
+
+    children
+}
+
+fn close_deck(
+    mut events: EventReader<CloseDeck>,
+    overlays: Query<Entity, With<DeckOverlay>>,
+    mut commands: Commands,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+
+    for entity in &overlays {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn visit_node(kind: NodeKind, player: &mut Player, commands: &mut Commands) {
+    match kind {
+        NodeKind::Battle | NodeKind::Elite | NodeKind::Boss => {
+            commands.insert_resource(NextState(MainState::Battle))
+        }
+        NodeKind::Rest => {
+            // only up to the max health, resting doesn't give shield
+            let heal = (player.max_health as f32 * REST_HEAL).round() as u32;
+            player.current_health = (player.current_health + heal).min(player.max_health);
+            commands.insert_resource(Service::Upgrade);
+            commands.insert_resource(NextState(MainState::Service));
+        }
+        NodeKind::Shop => commands.insert_resource(NextState(MainState::Shop)),
+        NodeKind::Event => commands.insert_resource(NextState(MainState::Event)),
+    }
+}
+
+fn reset_map(mut map: ResMut<RunMap>) {
+    *map = default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run_config::RunLength;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn same_seed_same_map() {
+        for length in RunLength::iter() {
+            let config = RunConfig::new(length);
+
+            for seed in 0..20 {
+                let map = RunMap::generate(seed, &config);
+
+                assert_eq!(map.layers, RunMap::generate(seed, &config).layers);
+            }
+        }
+    }
+
+    #[test]
+    fn one_boss_layer_per_boss_round() {
+        for length in RunLength::iter() {
+            let config = RunConfig::new(length);
+            let map = RunMap::generate(3, &config);
+
+            let bosses = map
+                .layers
+                .iter()
+                .filter(|x| x.iter().any(|x| x.kind == NodeKind::Boss))
+                .count();
+            assert_eq!(bosses, config.boss_rounds.len());
+            assert_eq!(map.layers.last().unwrap()[0].kind, NodeKind::Boss);
+        }
+    }
+
+    #[test]
+    fn every_node_leads_on() {
+        let map = RunMap::generate(11, &RunConfig::default());
+
+        for (i, layer) in map.layers.iter().enumerate().rev().skip(1) {
+            for node in layer {
+                assert!(!node.next.is_empty());
+                assert!(node.next.iter().all(|&x| x < map.layers[i + 1].len()));
+            }
+        }
+    }
+}
@@ -1,14 +1,42 @@
-use crate::board::Element;
+use crate::{
+    board::Element,
+    combat::{
+        SpellEffect, Status, StatusKind, Statuses, BASE_HEAL_PER_GEM, OVERHEAL_SHIELD, SHIELD_KEPT,
+    },
+};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
+/// The spell being cast and the energy left only matter during a turn, saves leave them out.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Player {
     pub max_health: u32,
     pub current_health: u32,
+    /// Absorbs damage before health does.
+    pub shield: u32,
     pub spells: Vec<Spell>,
+    #[serde(skip)]
     pub active_spell: Option<Spell>,
+    pub statuses: Statuses,
+    /// Cards drawn at the start of every turn.
+    pub hand_size: usize,
+    /// Cards drawn on top of the hand size, only on the next turn.
+    pub extra_draws: usize,
+    /// Energy restored at the start of every turn.
+    pub max_energy: u32,
+    /// Spent on casting spells.
+    #[serde(skip)]
+    pub energy: u32,
+    /// Earned in battles and spent in shops.
+    pub gold: u32,
 }
 
+pub const BASE_HAND_SIZE: usize = 5;
+pub const BASE_ENERGY: u32 = 3;
+/// Energy never drops below this, enough to cast any single card.
+pub const MIN_ENERGY: u32 = 2;
+
 impl Default for Player {
     fn default() -> Self {
         let max_health = 100;
@@ -16,6 +44,7 @@ impl Default for Player {
         Self {
             max_health,
             current_health: max_health,
+            shield: 0,
             spells: vec![
                 Spell::FIRE,
                 Spell::FIRE,
@@ -25,66 +54,444 @@ impl Default for Player {
                 Spell::THORNS,
                 Spell::RAY,
                 Spell::CURSE,
+                Spell::MEND,
+                Spell::MEND,
             ],
             active_spell: None,
+            statuses: default(),
+            hand_size: BASE_HAND_SIZE,
+            extra_draws: 0,
+            max_energy: BASE_ENERGY,
+            energy: BASE_ENERGY,
+            gold: 0,
         }
     }
 }
 
-#[derive(Clone, Component)]
+impl Player {
+    /// Restores health and turns part of the overheal into shield.
+    /// Returns how much health and shield were gained.
+    pub fn heal(&mut self, amount: u32) -> (u32, u32) {
+        let healed = amount.min(self.max_health - self.current_health);
+        let shielded = ((amount - healed) as f32 * OVERHEAL_SHIELD).round() as u32;
+
+        self.current_health += healed;
+        self.shield += shielded;
+
+        (healed, shielded)
+    }
+
+    /// Damages the shield before health. Returns the damage the shield did not absorb.
+    pub fn take_damage(&mut self, damage: u32) -> u32 {
+        let absorbed = damage.min(self.shield);
+        let unblocked = damage - absorbed;
+
+        self.shield -= absorbed;
+        self.current_health = self.current_health.saturating_sub(unblocked);
+
+        unblocked
+    }
+
+    /// How many cards to draw this turn, using up the extra draws. Always at least one card.
+    pub fn take_draw_count(&mut self, penalty: usize) -> usize {
+        (self.hand_size + std::mem::take(&mut self.extra_draws))
+            .saturating_sub(penalty)
+            .max(1)
+    }
+
+    pub fn refill_energy(&mut self) {
+        self.energy = self
+            .max_energy
+            .saturating_sub(self.statuses.energy_penalty())
+            .max(MIN_ENERGY);
+    }
+
+    /// Counts a merge for every card of the deck that went into one, upgrading the cards that
+    /// were merged often enough.
+    pub fn record_merge(&mut self, cards: &[usize], evolution: &Evolution) {
+        if cards.len() < 2 {
+            return;
+        }
+
+        for &i in cards {
+            let spell = &mut self.spells[i];
+            spell.merges += 1;
+
+            if !spell.upgraded && evolution.merges_to_upgrade == Some(spell.merges) {
+                *spell = spell.upgrade();
+            }
+        }
+    }
+
+    /// Adds a merged spell to the deck, unless a card with its name is already in it.
+    pub fn learn(&mut self, spell: &Spell) -> bool {
+        if self.spells.iter().any(|x| x.name == spell.name) {
+            return false;
+        }
+
+        self.spells.push(Spell {
+            merges: 0,
+            ..spell.clone()
+        });
+
+        true
+    }
+
+    pub fn decay_shield(&mut self) {
+        self.shield = (self.shield as f32 * SHIELD_KEPT).floor() as u32;
+    }
+
+    /// Drops what only lasts for one battle, before the next one starts.
+    pub fn start_battle(&mut self) {
+        self.shield = 0;
+        self.statuses = default();
+        self.extra_draws = 0;
+    }
+}
+
+/// Optional ways the deck grows from casting merged spells.
+pub struct Evolution {
+    /// Recipes marked to be learned add their spell to the deck the first time it is cast.
+    pub learn_recipes: bool,
+    /// Cards upgrade once they were merged this many times.
+    pub merges_to_upgrade: Option<u32>,
+}
+
+impl Default for Evolution {
+    fn default() -> Self {
+        Self {
+            learn_recipes: true,
+            merges_to_upgrade: Some(3),
+        }
+    }
+}
+
+#[derive(Clone, Component, Serialize, Deserialize)]
 pub struct Spell {
     pub name: Cow<'static, str>,
     pub elements: Cow<'static, [Element]>,
     pub attack: u32,
+    /// Extra health restored per matched Heal gem.
+    pub heal: u32,
+    pub effects: Cow<'static, [SpellEffect]>,
+    pub upgraded: bool,
+    /// Energy it takes to cast, merged spells cost as much as the cards that went into them.
+    pub cost: u32,
+    /// Most cards, this one included, it can be cast together with.
+    pub merge_limit: usize,
+    /// Times the card was merged with others, kept in the deck between battles.
+    pub merges: u32,
 }
 
+pub const UPGRADE_ATTACK: u32 = 2;
+pub const UPGRADE_HEAL: u32 = 2;
+
+/// Most cards that can ever be merged into one spell.
+pub const MAX_MERGE: usize = 3;
+
 impl Spell {
-    const FIRE: Self = Spell {
+    /// A stronger copy of the spell, only attack and heal it already has are raised.
+    pub fn upgrade(&self) -> Spell {
+        let mut spell = self.clone();
+
+        if spell.attack != 0 {
+            spell.attack += UPGRADE_ATTACK;
+        }
+        if spell.heal != 0 {
+            spell.heal += UPGRADE_HEAL;
+        }
+        spell.upgraded = true;
+
+        spell
+    }
+
+    /// Whether the spells can be cast together, which needs at least one and no more than
+    /// any of their merge limits.
+    pub fn can_merge(spells: &[&Spell]) -> bool {
+        !spells.is_empty() && spells.iter().all(|x| spells.len() <= x.merge_limit)
+    }
+
+    pub fn description(&self) -> String {
+        let elements: Vec<_> = self.elements.iter().map(|x| x.to_string()).collect();
+
+        let mut text = format!(
+            "{} ({}) {} energy",
+            self.name,
+            elements.join("/"),
+            self.cost
+        );
+        if self.upgraded {
+            text.insert(self.name.len(), '+');
+        }
+        if self.attack != 0 {
+            text += &format!(" {} attack", self.attack);
+        }
+        if self.heal != 0 {
+            text += &format!(" +{} heal", self.heal);
+        }
+        for effect in self.effects.iter() {
+            text += &format!(" {}", effect.description());
+        }
+        if self.merge_limit < MAX_MERGE {
+            text += &format!(" [{}]", self.merge_limit_text());
+        }
+
+        text
+    }
+
+    /// Everything the spell does, one sentence per line.
+    pub fn rules_text(&self) -> String {
+        let mut lines = vec![format!("Costs {} energy.", self.cost)];
+
+        let attacking: Vec<_> = self
+            .elements
+            .iter()
+            .filter(|x| **x != Element::Heal)
+            .map(|x| x.to_string())
+            .collect();
+        if self.attack != 0 && !attacking.is_empty() {
+            lines.push(format!(
+                "Deals {} damage for every matched {} gem.",
+                self.attack,
+                attacking.join(" or ")
+            ));
+        }
+        if self.heal != 0 {
+            lines.push(format!(
+                "Heals {} for every matched Heal gem.",
+                BASE_HEAL_PER_GEM + self.heal
+            ));
+        }
+
+        lines.extend(self.effects.iter().map(|x| x.rules_text()));
+
+        lines.push(match self.merge_limit {
+            1 => "Can't be merged.".into(),
+            2 => "Merges with up to 1 other card.".into(),
+            limit => format!("Merges with up to {} other cards.", limit - 1),
+        });
+        if self.upgraded {
+            lines.push("Upgraded.".into());
+        }
+        if self.merges != 0 {
+            lines.push(format!("Merged {} times.", self.merges));
+        }
+
+        lines.join("\n")
+    }
+
+    pub fn merge_limit_text(&self) -> String {
+        match self.merge_limit {
+            1 => "Cast alone".into(),
+            limit => format!("Merges {limit}"),
+        }
+    }
+
+    pub const FIRE: Self = Spell {
         name: Cow::Borrowed("Fire"),
         elements: Cow::Borrowed(&[Element::Fire]),
         attack: 2,
+        heal: 0,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 1,
+        merge_limit: MAX_MERGE,
+        merges: 0,
     };
 
-    const WAVE: Self = Spell {
+    pub const WAVE: Self = Spell {
         name: Cow::Borrowed("Wave"),
         elements: Cow::Borrowed(&[Element::Water]),
         attack: 2,
+        heal: 0,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 1,
+        merge_limit: MAX_MERGE,
+        merges: 0,
     };
 
-    const THORNS: Self = Spell {
+    pub const THORNS: Self = Spell {
         name: Cow::Borrowed("Thorns"),
         elements: Cow::Borrowed(&[Element::Grass]),
         attack: 2,
+        heal: 0,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 1,
+        merge_limit: MAX_MERGE,
+        merges: 0,
     };
 
-    const RAY: Self = Spell {
+    pub const RAY: Self = Spell {
         name: Cow::Borrowed("Ray"),
         elements: Cow::Borrowed(&[Element::Light]),
         attack: 3,
+        heal: 0,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 1,
+        merge_limit: MAX_MERGE,
+        merges: 0,
     };
 
-    const CURSE: Self = Spell {
+    pub const CURSE: Self = Spell {
         name: Cow::Borrowed("Curse"),
         elements: Cow::Borrowed(&[Element::Dark]),
         attack: 3,
+        heal: 0,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 1,
+        merge_limit: MAX_MERGE,
+        merges: 0,
     };
 
-    pub fn empty() -> Spell {
-        Spell {
-            name: Cow::Borrowed(""),
-            elements: default(),
-            attack: 0,
-        }
-    }
+    pub const MEND: Self = Spell {
+        name: Cow::Borrowed("Mend"),
+        elements: Cow::Borrowed(&[Element::Heal]),
+        attack: 0,
+        heal: 3,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 1,
+        merge_limit: MAX_MERGE,
+        merges: 0,
+    };
 
-    pub fn name_modifier(&self) -> &'static str {
-        match self.elements.first().unwrap() {
-            Element::Heal => "Healing",
-            Element::Dark => "Cursed",
-            Element::Water => "Frost",
-            Element::Fire => "Blaze",
-            Element::Grass => "Overgrown",
-            Element::Light => "Blinding",
-        }
-    }
+    pub const INFERNO: Self = Spell {
+        name: Cow::Borrowed("Inferno"),
+        elements: Cow::Borrowed(&[Element::Fire]),
+        attack: 4,
+        heal: 0,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 2,
+        merges: 0,
+    };
+
+    pub const TIDE: Self = Spell {
+        name: Cow::Borrowed("Tide"),
+        elements: Cow::Borrowed(&[Element::Water]),
+        attack: 4,
+        heal: 0,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 2,
+        merges: 0,
+    };
+
+    pub const BRAMBLE: Self = Spell {
+        name: Cow::Borrowed("Bramble"),
+        elements: Cow::Borrowed(&[Element::Grass]),
+        attack: 4,
+        heal: 0,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 2,
+        merges: 0,
+    };
+
+    pub const RENEW: Self = Spell {
+        name: Cow::Borrowed("Renew"),
+        elements: Cow::Borrowed(&[Element::Heal]),
+        attack: 0,
+        heal: 5,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 2,
+        merges: 0,
+    };
+
+    pub const HEX: Self = Spell {
+        name: Cow::Borrowed("Hex"),
+        elements: Cow::Borrowed(&[Element::Dark]),
+        attack: 5,
+        heal: 0,
+        effects: Cow::Borrowed(&[SpellEffect::Apply(Status {
+            kind: StatusKind::Vulnerable,
+            turns: 2,
+        })]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 2,
+        merges: 0,
+    };
+
+    pub const FLASH: Self = Spell {
+        name: Cow::Borrowed("Flash"),
+        elements: Cow::Borrowed(&[Element::Light]),
+        attack: 3,
+        heal: 0,
+        effects: Cow::Borrowed(&[SpellEffect::Apply(Status {
+            kind: StatusKind::Blinded,
+            turns: 1,
+        })]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 2,
+        merges: 0,
+    };
+
+    pub const PRISM: Self = Spell {
+        name: Cow::Borrowed("Prism"),
+        elements: Cow::Borrowed(&[Element::Fire, Element::Water, Element::Grass]),
+        attack: 3,
+        heal: 0,
+        effects: Cow::Borrowed(&[]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 1,
+        merges: 0,
+    };
+
+    pub const TEMPO: Self = Spell {
+        name: Cow::Borrowed("Tempo"),
+        elements: Cow::Borrowed(&[Element::Light]),
+        attack: 2,
+        heal: 0,
+        effects: Cow::Borrowed(&[SpellEffect::MoveTime(3.0), SpellEffect::Draw(1)]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 2,
+        merges: 0,
+    };
+
+    pub const KINDLE: Self = Spell {
+        name: Cow::Borrowed("Kindle"),
+        elements: Cow::Borrowed(&[Element::Fire]),
+        attack: 3,
+        heal: 0,
+        effects: Cow::Borrowed(&[SpellEffect::Convert {
+            from: Element::Grass,
+            to: Element::Fire,
+            gems: 5,
+        }]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 2,
+        merges: 0,
+    };
+
+    pub const LANCE: Self = Spell {
+        name: Cow::Borrowed("Lance"),
+        elements: Cow::Borrowed(&[Element::Grass]),
+        attack: 4,
+        heal: 0,
+        effects: Cow::Borrowed(&[
+            SpellEffect::Pierce,
+            SpellEffect::Amplify {
+                element: Element::Grass,
+                multiplier: 1.5,
+            },
+        ]),
+        upgraded: false,
+        cost: 2,
+        merge_limit: 2,
+        merges: 0,
+    };
 }
@@ -0,0 +1,266 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{ensure, Context};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::{
+    board::Element,
+    combat::SpellEffect,
+    player::{Spell, UPGRADE_ATTACK, UPGRADE_HEAL},
+    utils::Loading,
+};
+
+pub const RECIPES_PATH: &str = "spells/spells.recipes.ron";
+
+pub struct RecipesPlugin;
+
+impl Plugin for RecipesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<RecipeBook>()
+            .init_asset_loader::<RecipeBookLoader>()
+            .add_startup_system(load_recipes);
+    }
+}
+
+/// What a recipe needs from one of the spells being merged.
+#[derive(Debug, Clone, Deserialize)]
+pub enum Ingredient {
+    /// A spell with exactly this name.
+    Spell(String),
+    /// Any spell with this element.
+    Element(Element),
+}
+
+impl Ingredient {
+    fn matches(&self, spell: &Spell) -> bool {
+        match self {
+            Ingredient::Spell(name) => spell.name == name.as_str(),
+            Ingredient::Element(element) => spell.elements.contains(element),
+        }
+    }
+}
+
+/// Turns two spells into a named spell of its own instead of the fallback one.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Recipe {
+    /// Matched in either order.
+    pub ingredients: (Ingredient, Ingredient),
+    pub name: String,
+    /// Defaults to the elements of both spells.
+    #[serde(default)]
+    pub elements: Option<Vec<Element>>,
+    /// Defaults to the sum of both spells.
+    #[serde(default)]
+    pub attack: Option<u32>,
+    /// Defaults to the sum of both spells.
+    #[serde(default)]
+    pub heal: Option<u32>,
+    /// Run after the effects of the merged spells.
+    #[serde(default)]
+    pub effects: Vec<SpellEffect>,
+    /// Adds the spell to the deck the first time it is cast.
+    #[serde(default)]
+    pub learn: bool,
+}
+
+impl Recipe {
+    fn matches(&self, a: &Spell, b: &Spell) -> bool {
+        let (x, y) = &self.ingredients;
+
+        (x.matches(a) && y.matches(b)) || (x.matches(b) && y.matches(a))
+    }
+}
+
+/// Names merged spells that no recipe covers. Stats are summed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FallbackRule {
+    /// Put in front of the name when the second spell's name ends with the first one's.
+    pub same_prefix: String,
+    /// Put in front of the second spell's name, picked by the first spell's first element.
+    pub modifiers: HashMap<Element, String>,
+}
+
+impl Default for FallbackRule {
+    fn default() -> Self {
+        Self {
+            same_prefix: "Big".into(),
+            modifiers: Element::iter()
+                .map(|element| {
+                    let modifier = match element {
+                        Element::Heal => "Healing",
+                        Element::Dark => "Cursed",
+                        Element::Water => "Frost",
+                        Element::Fire => "Blaze",
+                        Element::Grass => "Overgrown",
+                        Element::Light => "Blinding",
+                    };
+
+                    (element, modifier.to_string())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl FallbackRule {
+    fn name(&self, a: &Spell, b: &Spell) -> String {
+        if b.name == a.name || b.name.ends_with(&format!(" {}", a.name)) {
+            return format!("{} {}", self.same_prefix, b.name);
+        }
+
+        match a.elements.first().and_then(|x| self.modifiers.get(x)) {
+            Some(modifier) => format!("{modifier} {}", b.name),
+            None => b.name.to_string(),
+        }
+    }
+}
+
+/// Every way two spells can be merged, read from [`RECIPES_PATH`].
+#[derive(Debug, Clone, Default, Deserialize, TypeUuid)]
+#[uuid = "5d0c8e3a-7b41-4e92-a6f5-1c9b2e7d4a03"]
+pub struct RecipeBook {
+    pub recipes: Vec<Recipe>,
+    #[serde(default)]
+    pub fallback: FallbackRule,
+}
+
+impl RecipeBook {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let book: RecipeBook = ron::de::from_bytes(bytes)?;
+
+        for recipe in &book.recipes {
+            ensure!(!recipe.name.is_empty(), "a recipe has an empty name");
+            ensure!(
+                recipe.elements.as_ref().map_or(true, |x| !x.is_empty()),
+                "recipe {} has no elements",
+                recipe.name
+            );
+        }
+
+        Ok(book)
+    }
+
+    /// Reads the recipe book straight from the assets folder, for tools without an asset server.
+    pub fn read_file(assets: &Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(assets.join(RECIPES_PATH))?;
+
+        Self::parse(&bytes).with_context(|| format!("invalid recipe book {RECIPES_PATH}"))
+    }
+
+    /// Whether the spell was made by a recipe that is learned once cast.
+    pub fn is_learned(&self, spell: &Spell) -> bool {
+        self.recipes.iter().any(|x| x.learn && x.name == spell.name)
+    }
+
+    /// Merges the spells from the last one backwards, so `[a, b, c]` merges `a` into the merge
+    /// of `b` and `c`. A single spell is cast as it is.
+    pub fn merge(&self, spells: &[&Spell]) -> Spell {
+        let (last, rest) = spells.split_last().expect("merging no spells");
+
+        rest.iter().rev().fold((*last).clone(), |merged, spell| {
+            self.merge_two(spell, &merged)
+        })
+    }
+
+    /// Merges two spells with the first recipe that takes them, or the fallback rule.
+    fn merge_two(&self, a: &Spell, b: &Spell) -> Spell {
+        let elements: Vec<_> = a
+            .elements
+            .iter()
+            .chain(b.elements.iter())
+            .copied()
+            .collect();
+        let mut effects: Vec<_> = a.effects.iter().chain(b.effects.iter()).copied().collect();
+        let upgrades = a.upgraded as u32 + b.upgraded as u32;
+        let merge_limit = a.merge_limit.min(b.merge_limit);
+
+        match self.recipes.iter().find(|x| x.matches(a, b)) {
+            Some(recipe) => {
+                effects.extend(recipe.effects.iter().copied());
+
+                // recipes with their own stats still keep the upgrades of what went into them
+                let upgrade = |stat: u32, bonus: u32| {
+                    if stat == 0 {
+                        0
+                    } else {
+                        stat + upgrades * bonus
+                    }
+                };
+
+                Spell {
+                    name: recipe.name.clone().into(),
+                    elements: recipe.elements.clone().unwrap_or(elements).into(),
+                    attack: recipe
+                        .attack
+                        .map(|x| upgrade(x, UPGRADE_ATTACK))
+                        .unwrap_or(a.attack + b.attack),
+                    heal: recipe
+                        .heal
+                        .map(|x| upgrade(x, UPGRADE_HEAL))
+                        .unwrap_or(a.heal + b.heal),
+                    effects: effects.into(),
+                    upgraded: upgrades != 0,
+                    cost: a.cost + b.cost,
+                    merge_limit,
+                    merges: 0,
+                }
+            }
+            None => Spell {
+                name: self.fallback.name(a, b).into(),
+                elements: elements.into(),
+                attack: a.attack + b.attack,
+                heal: a.heal + b.heal,
+                effects: effects.into(),
+                upgraded: upgrades != 0,
+                cost: a.cost + b.cost,
+                merge_limit,
+                merges: 0,
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+struct RecipeBookLoader;
+
+impl AssetLoader for RecipeBookLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            load_context.set_default_asset(LoadedAsset::new(RecipeBook::parse(bytes)?));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["recipes.ron"]
+    }
+}
+
+pub struct Recipes {
+    pub book: Handle<RecipeBook>,
+}
+
+fn load_recipes(
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<Loading>,
+    mut commands: Commands,
+) {
+    let book = asset_server.load(RECIPES_PATH);
+
+    loading.assets.push(book.clone_untyped());
+
+    commands.insert_resource(Recipes { book });
+}
@@ -0,0 +1,234 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use strum_macros::Display;
+
+use crate::{
+    main_state::{Difficulty, MainState},
+    map::{NodeKind, RunMap},
+    player::{Player, Spell},
+    prefab::*,
+    run_config::RunConfig,
+    ui::*,
+};
+
+pub struct RewardsPlugin;
+
+impl Plugin for RewardsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(OnClickPlugin::<PickReward>::new())
+            .add_enter_system(MainState::Reward, show_reward_screen)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(MainState::Reward)
+                    .with_system(pick_reward)
+                    .into(),
+            );
+    }
+}
+
+pub const REWARD_CHOICES: usize = 3;
+pub const BATTLE_GOLD: u32 = 20;
+pub const ELITE_GOLD: u32 = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Rarity {
+    Common,
+    Rare,
+    Epic,
+}
+
+impl Rarity {
+    /// Rarer cards get more likely the further the run goes.
+    pub fn weight(&self, round: u32) -> f32 {
+        let round = round as f32;
+
+        match self {
+            Rarity::Common => 10.0,
+            Rarity::Rare => 1.0 + round,
+            Rarity::Epic => (round - 3.0).max(0.0),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Rarity::Common => Color::BLACK,
+            Rarity::Rare => Color::rgb(0.1, 0.3, 0.9),
+            Rarity::Epic => Color::PURPLE,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RewardCard {
+    pub spell: Spell,
+    pub rarity: Rarity,
+}
+
+impl RewardCard {
+    pub fn description(&self) -> String {
+        format!("{} {}", self.rarity, self.spell.description())
+    }
+}
+
+pub fn reward_pool() -> Vec<RewardCard> {
+    use Rarity::*;
+
+    [
+        (Spell::FIRE, Common),
+        (Spell::WAVE, Common),
+        (Spell::THORNS, Common),
+        (Spell::RAY, Common),
+        (Spell::CURSE, Common),
+        (Spell::MEND, Common),
+        (Spell::INFERNO, Rare),
+        (Spell::TIDE, Rare),
+        (Spell::BRAMBLE, Rare),
+        (Spell::RENEW, Rare),
+        (Spell::TEMPO, Rare),
+        (Spell::KINDLE, Rare),
+        (Spell::HEX, Epic),
+        (Spell::FLASH, Epic),
+        (Spell::PRISM, Epic),
+        (Spell::LANCE, Epic),
+    ]
+    .into_iter()
+    .map(|(spell, rarity)| RewardCard { spell, rarity })
+    .collect()
+}
+
+/// Picks up to `count` different cards from the pool, weighted by rarity for the round.
+pub fn roll_rewards(round: u32, count: usize, rng: &fastrand::Rng) -> Vec<RewardCard> {
+    let mut pool = reward_pool();
+    let mut rewards = vec![];
+
+    while rewards.len() < count && !pool.is_empty() {
+        let total: f32 = pool.iter().map(|x| x.rarity.weight(round)).sum();
+        let mut roll = rng.f32() * total;
+
+        let index = pool
+            .iter()
+            .position(|x| {
+                roll -= x.rarity.weight(round);
+                roll < 0.0
+            })
+            .unwrap_or(pool.len() - 1);
+
+        rewards.push(pool.swap_remove(index));
+    }
+
+    rewards
+}
+
+/// The cards offered on the reward screen.
+struct RewardOffer(Vec<RewardCard>);
+
+/// Index of the picked card, or none when skipping.
+#[derive(Clone, Copy)]
+struct PickReward(Option<usize>);
+
+#[derive(Component)]
+struct RewardScreen;
+
+fn show_reward_screen(
+    difficulty: Res<Difficulty>,
+    config: Res<RunConfig>,
+    map: Res<RunMap>,
+    mut player: ResMut<Player>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let rewards = roll_rewards(
+        config.enemy_round(difficulty.round),
+        REWARD_CHOICES,
+        &fastrand::Rng::new(),
+    );
+
+    let gold = match map.current().map(|x| x.kind) {
+        Some(NodeKind::Elite) => ELITE_GOLD,
+        _ => BATTLE_GOLD,
+    };
+    player.gold += gold;
+
+    let mut children: Vec<Child> = vec![
+        TextPrefab {
+            text: format!("+{gold} gold"),
+            size: 40.0,
+            color: Color::GOLD,
+            font: font.clone(),
+        }
+        .into(),
+        TextPrefab {
+            text: "Choose a card".into(),
+            size: 60.0,
+            color: Color::WHITE,
+            font: font.clone(),
+        }
+        .into(),
+    ];
+
+    children.extend(rewards.iter().enumerate().map(|(i, reward)| {
+        Child::from(ButtonPrefab {
+            on_click: PickReward(Some(i)),
+            child: TextPrefab {
+                text: reward.description(),
+                size: 30.0,
+                color: reward.rarity.color(),
+                font: font.clone(),
+            },
+        })
+    }));
+
+    children.push(
+        ButtonPrefab {
+            on_click: PickReward(None),
+            child: TextPrefab {
+                text: "Skip".into(),
+                size: 30.0,
+                color: Color::BLACK,
+                font,
+            },
+        }
+        .into(),
+    );
+
+    commands
+        .spawn_prefab(FullScreen {
+            color: Color::BLACK,
+            child: VBox {
+                gap: 30.0,
+                children,
+            },
+        })
+        .insert(RewardScreen);
+
+    commands
+        .spawn_bundle(Camera3dBundle::default())
+        .insert(RewardScreen);
+
+    commands.insert_resource(RewardOffer(rewards));
+}
+
+fn pick_reward(
+    mut events: EventReader<PickReward>,
+    offer: Res<RewardOffer>,
+    screens: Query<Entity, With<RewardScreen>>,
+    mut player: ResMut<Player>,
+    mut commands: Commands,
+) {
+    let pick = match events.iter().next() {
+        Some(pick) => pick,
+        None => return,
+    };
+
+    if let Some(reward) = pick.0.and_then(|i| offer.0.get(i)) {
+        player.spells.push(reward.spell.clone());
+    }
+
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands.remove_resource::<RewardOffer>();
+    commands.insert_resource(NextState(MainState::Map));
+}
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
+
+/// How long a run is, picked before it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter, Serialize, Deserialize)]
+pub enum RunLength {
+    Short,
+    Standard,
+    Long,
+}
+
+impl RunLength {
+    pub fn description(&self) -> String {
+        match self {
+            RunLength::Short => "4 rounds".into(),
+            RunLength::Standard => format!("{STANDARD_ROUNDS} rounds"),
+            RunLength::Long => format!("{LONG_ACTS} acts of {LONG_ACT_ROUNDS} rounds"),
+        }
+    }
+}
+
+/// A part of the run with its own pool of enemies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Act {
+    pub last_round: u32,
+    /// Enemies and environments are picked as if the act went from the first of these rounds
+    /// to the last, which are the rounds in their definitions.
+    pub enemy_rounds: (u32, u32),
+}
+
+/// The shape of a run. Rounds are counted from 1 and each of them is one fight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunConfig {
+    pub length: RunLength,
+    pub rounds: u32,
+    /// Rounds elites can show up in.
+    pub elite_rounds: Vec<u32>,
+    /// Rounds fought against a boss, the last round always is one.
+    pub boss_rounds: Vec<u32>,
+    /// Rounds followed by a layer of rests, shops and events on the map.
+    pub interlude_rounds: Vec<u32>,
+    pub acts: Vec<Act>,
+}
+
+/// The length enemies and rewards were balanced for.
+pub const STANDARD_ROUNDS: u32 = 8;
+const LONG_ACTS: u32 = 3;
+const LONG_ACT_ROUNDS: u32 = 6;
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self::new(RunLength::Standard)
+    }
+}
+
+impl RunConfig {
+    pub fn new(length: RunLength) -> Self {
+        match length {
+            RunLength::Short => Self {
+                length,
+                rounds: 4,
+                elite_rounds: vec![2, 3],
+                boss_rounds: vec![4],
+                interlude_rounds: vec![1, 2, 3],
+                acts: vec![Act {
+                    last_round: 4,
+                    enemy_rounds: (1, STANDARD_ROUNDS),
+                }],
+            },
+            RunLength::Standard => Self {
+                length,
+                rounds: STANDARD_ROUNDS,
+                elite_rounds: (3..STANDARD_ROUNDS).collect(),
+                boss_rounds: vec![STANDARD_ROUNDS],
+                interlude_rounds: vec![2, 4, 6, 7],
+                acts: vec![Act {
+                    last_round: STANDARD_ROUNDS,
+                    enemy_rounds: (1, STANDARD_ROUNDS),
+                }],
+            },
+            RunLength::Long => {
+                let mut config = Self {
+                    length,
+                    rounds: LONG_ACTS * LONG_ACT_ROUNDS,
+                    elite_rounds: vec![],
+                    boss_rounds: vec![],
+                    interlude_rounds: vec![],
+                    acts: vec![],
+                };
+
+                // only the last act reaches the real bosses, the others end on the
+                // strongest enemy of their pool
+                let pools = [(1, 4), (3, 6), (5, STANDARD_ROUNDS)];
+
+                for (act, enemy_rounds) in pools.into_iter().enumerate() {
+                    let start = act as u32 * LONG_ACT_ROUNDS;
+                    let last_round = start + LONG_ACT_ROUNDS;
+
+                    config.elite_rounds.extend(start + 3..last_round);
+                    config.boss_rounds.push(last_round);
+                    config
+                        .interlude_rounds
+                        .extend([start + 2, start + 4, start + 5]);
+                    if last_round < config.rounds {
+                        config.interlude_rounds.push(last_round);
+                    }
+
+                    config.acts.push(Act {
+                        last_round,
+                        enemy_rounds,
+                    });
+                }
+
+                config
+            }
+        }
+    }
+
+    pub fn is_boss_round(&self, round: u32) -> bool {
+        self.boss_rounds.contains(&round)
+    }
+
+    pub fn is_elite_round(&self, round: u32) -> bool {
+        self.elite_rounds.contains(&round)
+    }
+
+    pub fn is_interlude_round(&self, round: u32) -> bool {
+        self.interlude_rounds.contains(&round)
+    }
+
+    /// The round enemies, environments and rewards are picked for, spread over the enemy
+    /// rounds of the act the round is in.
+    pub fn enemy_round(&self, round: u32) -> u32 {
+        let i = self
+            .acts
+            .iter()
+            .position(|x| round <= x.last_round)
+            .unwrap_or(self.acts.len() - 1);
+        let act = &self.acts[i];
+        let first = if i == 0 {
+            1
+        } else {
+            self.acts[i - 1].last_round + 1
+        };
+
+        let progress = if act.last_round > first {
+            (round.clamp(first, act.last_round) - first) as f32 / (act.last_round - first) as f32
+        } else {
+            1.0
+        };
+
+        let (start, end) = act.enemy_rounds;
+        (start as f32 + (end as f32 - start as f32) * progress).round() as u32
+    }
+
+    /// How many rounds of a standard run one round of this run is worth, so enemies reach the
+    /// same strength by the end of any run.
+    pub fn pace(&self) -> f32 {
+        (STANDARD_ROUNDS - 1) as f32 / (self.rounds.max(2) - 1) as f32
+    }
+}
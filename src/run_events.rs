@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{main_state::MainState, player::Player, prefab::*, services::Service, ui::*};
+
+pub struct RunEventsPlugin;
+
+impl Plugin for RunEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(OnClickPlugin::<EventChoice>::new())
+            .add_enter_system(MainState::Event, show_event_screen)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(MainState::Event)
+                    .with_system(pick_event_choice)
+                    .into(),
+            );
+    }
+}
+
+/// Something found on an event node of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunEvent {
+    Shrine,
+    Spring,
+    Altar,
+    Smith,
+}
+
+impl RunEvent {
+    const ALL: [RunEvent; 4] = [
+        RunEvent::Shrine,
+        RunEvent::Spring,
+        RunEvent::Altar,
+        RunEvent::Smith,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            RunEvent::Shrine => "Shrine",
+            RunEvent::Spring => "Spring",
+            RunEvent::Altar => "Altar",
+            RunEvent::Smith => "Wandering Smith",
+        }
+    }
+
+    fn text(&self) -> &'static str {
+        match self {
+            RunEvent::Shrine => "A quiet shrine asks for one of your spells.",
+            RunEvent::Spring => "A clear spring bubbles between the rocks.",
+            RunEvent::Altar => "A bloodstained altar offers gold for blood.",
+            RunEvent::Smith => "A smith offers to sharpen one of your spells.",
+        }
+    }
+
+    fn choices(&self) -> Vec<EventChoice> {
+        match self {
+            RunEvent::Shrine => vec![EventChoice::Service(Service::Remove { price: 0 })],
+            RunEvent::Spring => vec![EventChoice::Heal(20), EventChoice::MaxHealth(6)],
+            RunEvent::Altar => vec![EventChoice::TradeHealth {
+                health: 10,
+                gold: 50,
+            }],
+            RunEvent::Smith => vec![EventChoice::Service(Service::Upgrade)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EventChoice {
+    /// Goes on to the service screen.
+    Service(Service),
+    Heal(u32),
+    /// Raises the max health and heals by as much.
+    MaxHealth(u32),
+    TradeHealth {
+        health: u32,
+        gold: u32,
+    },
+    Leave,
+}
+
+impl EventChoice {
+    fn label(&self) -> String {
+        match self {
+            EventChoice::Service(Service::Remove { .. }) => "Remove a card".into(),
+            EventChoice::Service(Service::Upgrade) => "Upgrade a card".into(),
+            EventChoice::Heal(amount) => format!("Drink: heal {amount}"),
+            EventChoice::MaxHealth(amount) => format!("Bathe: +{amount} max health"),
+            EventChoice::TradeHealth { health, gold } => {
+                format!("Bleed: lose {health} health, gain {gold} gold")
+            }
+            EventChoice::Leave => "Leave".into(),
+        }
+    }
+
+    fn can_pick(&self, player: &Player) -> bool {
+        match self {
+            // the deck always needs enough cards for a full hand
+            EventChoice::Service(Service::Remove { .. }) => player.spells.len() > player.hand_size,
+            EventChoice::Service(Service::Upgrade) => player.spells.iter().any(|x| !x.upgraded),
+            EventChoice::TradeHealth { health, .. } => player.current_health > *health,
+            _ => true,
+        }
+    }
+
+    fn apply(&self, player: &mut Player, commands: &mut Commands) {
+        match *self {
+            EventChoice::Service(service) => {
+                commands.insert_resource(service);
+                commands.insert_resource(NextState(MainState::Service));
+                return;
+            }
+            EventChoice::Heal(amount) => {
+                player.current_health = (player.current_health + amount).min(player.max_health);
+            }
+            EventChoice::MaxHealth(amount) => {
+                player.max_health += amount;
+                player.current_health += amount;
+            }
+            EventChoice::TradeHealth { health, gold } => {
+                player.current_health = player.current_health.saturating_sub(health);
+                player.gold += gold;
+            }
+            EventChoice::Leave => {}
+        }
+
+        commands.insert_resource(NextState(MainState::Map));
+    }
+}
+
+#[derive(Component)]
+struct EventScreen;
+
+fn show_event_screen(player: Res<Player>, asset_server: Res<AssetServer>, mut commands: Commands) {
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let event = RunEvent::ALL[fastrand::usize(..RunEvent::ALL.len())];
+
+    let mut children: Vec<Child> = vec![
+        TextPrefab {
+            text: event.title().into(),
+            size: 60.0,
+            color: Color::WHITE,
+            font: font.clone(),
+        }
+        .into(),
+        TextPrefab {
+            text: event.text().into(),
+            size: 30.0,
+            color: Color::WHITE,
+            font: font.clone(),
+        }
+        .into(),
+    ];
+
+    children.extend(
+        event
+            .choices()
+            .into_iter()
+            .filter(|x| x.can_pick(&player))
+            .chain([EventChoice::Leave])
+            .map(|choice| {
+                Child::from(ButtonPrefab {
+                    on_click: choice,
+                    child: TextPrefab {
+                        text: choice.label(),
+                        size: 30.0,
+                        color: Color::BLACK,
+                        font: font.clone(),
+                    },
+                })
+            }),
+    );
+
+    commands
+        .spawn_prefab(FullScreen {
+            color: Color::BLACK,
+            child: VBox {
+                gap: 20.0,
+                children,
+            },
+        })
+        .insert(EventScreen);
+
+    commands
+        .spawn_bundle(Camera3dBundle::default())
+        .insert(EventScreen);
+}
+
+fn pick_event_choice(
+    mut events: EventReader<EventChoice>,
+    screens: Query<Entity, With<EventScreen>>,
+    mut player: ResMut<Player>,
+    mut commands: Commands,
+) {
+    let choice = match events.iter().next() {
+        Some(choice) => *choice,
+        None => return,
+    };
+
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    choice.apply(&mut player, &mut commands);
+}
@@ -0,0 +1,461 @@
+use std::{io::ErrorKind, path::Path};
+
+use anyhow::{ensure, Context};
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::{
+    combat::CombatStats,
+    main_state::{Difficulty, DifficultyLevel, MainState, Modifier},
+    map::RunMap,
+    player::Player,
+    prefab::*,
+    run_config::{RunConfig, RunLength},
+    ui::*,
+};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(OnClickPlugin::<MenuAction>::new())
+            .add_enter_system(MainState::Menu, show_menu_screen)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(MainState::Menu)
+                    .with_system(press_menu_buttons)
+                    .into(),
+            )
+            .add_enter_system(MainState::Map, write_save)
+            .add_enter_system(MainState::Death, delete_save)
+            .add_enter_system(MainState::Win, delete_save);
+    }
+}
+
+pub const SAVE_PATH: &str = "saves/run.ron";
+/// Raised whenever the save format or the map generation changes, older saves are refused.
+pub const SAVE_VERSION: u32 = 3;
+
+/// Everything needed to pick a run up again from the map.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunSave {
+    pub version: u32,
+    pub player: Player,
+    pub difficulty: Difficulty,
+    pub config: RunConfig,
+    pub stats: CombatStats,
+    pub map_seed: u64,
+    pub map_position: Option<(usize, usize)>,
+}
+
+/// Read before the rest of the save, so saves of other versions are reported as such instead
+/// of failing to parse.
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+impl RunSave {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let header: SaveHeader = ron::de::from_bytes(bytes).context("the save is corrupt")?;
+        ensure!(
+            header.version == SAVE_VERSION,
+            "the save is from version {} of the game, this one reads version {SAVE_VERSION}",
+            header.version
+        );
+
+        let save: RunSave = ron::de::from_bytes(bytes).context("the save is corrupt")?;
+
+        let player = &save.player;
+        ensure!(!player.spells.is_empty(), "the saved deck is empty");
+        ensure!(
+            player.current_health > 0 && player.current_health <= player.max_health,
+            "the saved health is {}/{}",
+            player.current_health,
+            player.max_health
+        );
+
+        if let Some((layer, index)) = save.map_position {
+            let map = RunMap::generate(save.map_seed, &save.config);
+            ensure!(
+                map.layers.get(layer).map_or(false, |x| index < x.len()),
+                "the saved map position is not on the map"
+            );
+        }
+
+        Ok(save)
+    }
+
+    /// Reads the save file, which is none when there is no run to continue.
+    pub fn read() -> anyhow::Result<Option<Self>> {
+        // browsers have no files to keep a save in
+        if cfg!(target_arch = "wasm32") {
+            return Ok(None);
+        }
+
+        match std::fs::read(SAVE_PATH) {
+            Ok(bytes) => Self::parse(&bytes).map(Some),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error).context("the save can't be read"),
+        }
+    }
+
+    pub fn write(&self) -> anyhow::Result<()> {
+        if cfg!(target_arch = "wasm32") {
+            return Ok(());
+        }
+
+        if let Some(folder) = Path::new(SAVE_PATH).parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+
+        let text = ron::ser::to_string_pretty(self, default())?;
+        std::fs::write(SAVE_PATH, text)?;
+
+        Ok(())
+    }
+}
+
+fn write_save(
+    player: Res<Player>,
+    difficulty: Res<Difficulty>,
+    config: Res<RunConfig>,
+    stats: Res<CombatStats>,
+    map: Res<RunMap>,
+) {
+    let save = RunSave {
+        version: SAVE_VERSION,
+        player: player.clone(),
+        difficulty: difficulty.clone(),
+        config: config.clone(),
+        stats: stats.clone(),
+        map_seed: map.seed,
+        map_position: map.position,
+    };
+
+    if let Err(error) = save.write() {
+        warn!("Failed to save the run to {SAVE_PATH}: {error:#}");
+    }
+}
+
+/// Finished runs can't be continued.
+fn delete_save() {
+    if cfg!(target_arch = "wasm32") {
+        return;
+    }
+
+    match std::fs::remove_file(SAVE_PATH) {
+        Err(error) if error.kind() != ErrorKind::NotFound => {
+            warn!("Failed to delete the save {SAVE_PATH}: {error}");
+        }
+        _ => {}
+    }
+}
+
+#[derive(Clone, Copy)]
+enum MenuAction {
+    NewRun,
+    Continue,
+    PickLevel(DifficultyLevel),
+    PickLength(RunLength),
+    ToggleModifier(Modifier),
+    Start,
+}
+
+#[derive(Component)]
+struct MenuScreen;
+
+/// The run that can be continued from the menu.
+struct SavedRun(RunSave);
+
+fn menu_button(on_click: MenuAction, text: String, size: f32, font: &Handle<Font>) -> Child {
+    ButtonPrefab {
+        on_click,
+        child: TextPrefab {
+            text,
+            size,
+            color: Color::BLACK,
+            font: font.clone(),
+        },
+    }
+    .into()
+}
+
+fn spawn_menu_screen(children: Vec<Child>, commands: &mut Commands) {
+    commands
+        .spawn_prefab(FullScreen {
+            color: Color::BLACK,
+            child: VBox {
+                gap: 30.0,
+                children,
+            },
+        })
+        .insert(MenuScreen);
+
+    commands
+        .spawn_bundle(Camera3dBundle::default())
+        .insert(MenuScreen);
+}
+
+fn show_menu_screen(asset_server: Res<AssetServer>, mut commands: Commands) {
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    let mut children: Vec<Child> = vec![TextPrefab {
+        text: "Magic Mixologist".into(),
+        size: 80.0,
+        color: Color::WHITE,
+        font: font.clone(),
+    }
+    .into()];
+
+    match RunSave::read() {
+        Ok(Some(save)) => {
+            let text = format!(
+                "Continue ({}, {})",
+                save.config.length,
+                save.difficulty.label()
+            );
+            children.push(menu_button(MenuAction::Continue, text, 40.0, &font));
+            commands.insert_resource(SavedRun(save));
+        }
+        Ok(None) => {}
+        Err(error) => {
+            warn!("Failed to load the save {SAVE_PATH}: {error:#}");
+
+            children.push(
+                TextPrefab {
+                    text: format!("The saved run can't be continued: {error:#}"),
+                    size: 24.0,
+                    color: Color::ORANGE_RED,
+                    font: font.clone(),
+                }
+                .into(),
+            );
+        }
+    }
+
+    children.push(menu_button(
+        MenuAction::NewRun,
+        "New Run".into(),
+        40.0,
+        &font,
+    ));
+
+    spawn_menu_screen(children, &mut commands);
+}
+
+/// Picks the level, length and modifiers of a new run.
+fn difficulty_children(
+    difficulty: &Difficulty,
+    config: &RunConfig,
+    font: &Handle<Font>,
+) -> Vec<Child> {
+    let mut children: Vec<Child> = vec![TextPrefab {
+        text: "Difficulty".into(),
+        size: 60.0,
+        color: Color::WHITE,
+        font: font.clone(),
+    }
+    .into()];
+
+    children.extend(DifficultyLevel::iter().map(|level| {
+        let mark = if level == difficulty.level {
+            "(o)"
+        } else {
+            "( )"
+        };
+        menu_button(
+            MenuAction::PickLevel(level),
+            format!("{mark} {level}"),
+            30.0,
+            font,
+        )
+    }));
+
+    children.push(
+        TextPrefab {
+            text: "Length".into(),
+            size: 40.0,
+            color: Color::WHITE,
+            font: font.clone(),
+        }
+        .into(),
+    );
+
+    children.extend(RunLength::iter().map(|length| {
+        let mark = if length == config.length {
+            "(o)"
+        } else {
+            "( )"
+        };
+        menu_button(
+            MenuAction::PickLength(length),
+            format!("{mark} {length}: {}", length.description()),
+            30.0,
+            font,
+        )
+    }));
+
+    children.push(
+        TextPrefab {
+            text: "Modifiers".into(),
+            size: 40.0,
+            color: Color::WHITE,
+            font: font.clone(),
+        }
+        .into(),
+    );
+
+    children.extend(Modifier::iter().map(|modifier| {
+        let mark = if difficulty.has(modifier) {
+            "[x]"
+        } else {
+            "[ ]"
+        };
+        menu_button(
+            MenuAction::ToggleModifier(modifier),
+            format!("{mark} {}", modifier.description()),
+            24.0,
+            font,
+        )
+    }));
+
+    children.push(menu_button(MenuAction::Start, "Start".into(), 40.0, font));
+
+    children
+}
+
+#[allow(clippy::too_many_arguments)]
+fn press_menu_buttons(
+    mut events: EventReader<MenuAction>,
+    screens: Query<Entity, With<MenuScreen>>,
+    saved: Option<Res<SavedRun>>,
+    mut player: ResMut<Player>,
+    mut difficulty: ResMut<Difficulty>,
+    mut config: ResMut<RunConfig>,
+    mut stats: ResMut<CombatStats>,
+    mut map: ResMut<RunMap>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let action = match events.iter().next() {
+        Some(action) => *action,
+        None => return,
+    };
+
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    match action {
+        MenuAction::Continue => {
+            if let Some(saved) = saved {
+                let save = saved.0.clone();
+
+                *player = save.player;
+                *difficulty = save.difficulty;
+                *stats = save.stats;
+                *map = RunMap::generate(save.map_seed, &save.config);
+                *config = save.config;
+                map.position = save.map_position;
+            }
+        }
+        MenuAction::NewRun => {}
+        MenuAction::PickLevel(level) => {
+            *difficulty = Difficulty::new(level, difficulty.modifiers.clone());
+        }
+        MenuAction::PickLength(length) => *config = RunConfig::new(length),
+        MenuAction::ToggleModifier(modifier) => difficulty.toggle(modifier),
+        MenuAction::Start => *map = RunMap::generate(fastrand::u64(..), &config),
+    }
+
+    // everything but starting a run stays on the difficulty screen
+    if !matches!(action, MenuAction::Continue | MenuAction::Start) {
+        let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+        spawn_menu_screen(
+            difficulty_children(&difficulty, &config, &font),
+            &mut commands,
+        );
+        return;
+    }
+
+    commands.remove_resource::<SavedRun>();
+    commands.insert_resource(NextState(MainState::Map));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save() -> RunSave {
+        let config = RunConfig::new(RunLength::Long);
+        let map = RunMap::generate(7, &config);
+
+        RunSave {
+            version: SAVE_VERSION,
+            player: Player::default(),
+            difficulty: Difficulty::new(DifficultyLevel::Hard, vec![Modifier::RareHeals]),
+            config,
+            stats: CombatStats::default(),
+            map_seed: map.seed,
+            map_position: Some((1, map.layers[1].len() - 1)),
+        }
+    }
+
+    fn parse(save: &RunSave) -> anyhow::Result<RunSave> {
+        let text = ron::ser::to_string_pretty(save, default()).unwrap();
+
+        RunSave::parse(text.as_bytes())
+    }
+
+    #[test]
+    fn round_trip() {
+        let save = save();
+        let parsed = parse(&save).unwrap();
+
+        assert_eq!(parsed.version, SAVE_VERSION);
+        assert_eq!(parsed.player.spells.len(), save.player.spells.len());
+        assert_eq!(parsed.player.current_health, save.player.current_health);
+        assert_eq!(parsed.difficulty.level, DifficultyLevel::Hard);
+        assert!(parsed.difficulty.has(Modifier::RareHeals));
+        assert_eq!(parsed.config.length, RunLength::Long);
+        assert_eq!(parsed.map_seed, save.map_seed);
+        assert_eq!(parsed.map_position, save.map_position);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let save = RunSave {
+            version: SAVE_VERSION + 1,
+            ..save()
+        };
+
+        assert!(parse(&save).is_err());
+    }
+
+    #[test]
+    fn rejects_no_health() {
+        let mut save = save();
+        save.player.current_health = 0;
+
+        assert!(parse(&save).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_deck() {
+        let mut save = save();
+        save.player.spells.clear();
+
+        assert!(parse(&save).is_err());
+    }
+
+    #[test]
+    fn rejects_positions_off_the_map() {
+        let mut save = save();
+        save.map_position = Some((1, 99));
+
+        assert!(parse(&save).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{main_state::MainState, player::Player, prefab::*, ui::*};
+
+pub struct ServicesPlugin;
+
+impl Plugin for ServicesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(OnClickPlugin::<PickCard>::new())
+            .add_enter_system(MainState::Service, show_service_screen)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(MainState::Service)
+                    .with_system(use_service)
+                    .into(),
+            );
+    }
+}
+
+/// Something done to one card of the deck between battles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Service {
+    /// Makes a card that was not upgraded yet stronger.
+    Upgrade,
+    /// Takes a card out of the deck for the rest of the run, for a price paid only once a
+    /// card is picked.
+    Remove { price: u32 },
+}
+
+impl Service {
+    fn title(&self) -> &'static str {
+        match self {
+            Service::Upgrade => "Forge: upgrade a card",
+            Service::Remove { .. } => "Shrine: remove a card",
+        }
+    }
+
+    fn can_target(&self, player: &Player, card: usize) -> bool {
+        match self {
+            Service::Upgrade => !player.spells[card].upgraded,
+            // the deck always needs enough cards for a full hand
+            Service::Remove { price } => {
+                player.spells.len() > player.hand_size && player.gold >= *price
+            }
+        }
+    }
+
+    fn apply(&self, player: &mut Player, card: usize) {
+        match self {
+            Service::Upgrade => player.spells[card] = player.spells[card].upgrade(),
+            Service::Remove { price } => {
+                player.gold -= *price;
+                player.spells.remove(card);
+            }
+        }
+    }
+}
+
+/// Index of the card in [`Player::spells`], or none when leaving.
+#[derive(Clone, Copy)]
+struct PickCard(Option<usize>);
+
+#[derive(Component)]
+struct ServiceScreen;
+
+fn show_service_screen(
+    service: Res<Service>,
+    player: Res<Player>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+
+    let mut children: Vec<Child> = vec![TextPrefab {
+        text: service.title().into(),
+        size: 50.0,
+        color: Color::WHITE,
+        font: font.clone(),
+    }
+    .into()];
+
+    children.extend(
+        (0..player.spells.len())
+            .filter(|&i| service.can_target(&player, i))
+            .map(|i| {
+                Child::from(ButtonPrefab {
+                    on_click: PickCard(Some(i)),
+                    child: TextPrefab {
+                        text: player.spells[i].description(),
+                        size: 24.0,
+                        color: Color::BLACK,
+                        font: font.clone(),
+                    },
+                })
+            }),
+    );
+
+    children.push(
+        ButtonPrefab {
+            on_click: PickCard(None),
+            child: TextPrefab {
+                text: "Leave".into(),
+                size: 30.0,
+                color: Color::BLACK,
+                font,
+            },
+        }
+        .into(),
+    );
+
+    commands
+        .spawn_prefab(FullScreen {
+            color: Color::BLACK,
+            child: VBox {
+                gap: 10.0,
+                children,
+            },
+        })
+        .insert(ServiceScreen);
+
+    commands
+        .spawn_bundle(Camera3dBundle::default())
+        .insert(ServiceScreen);
+}
+
+fn use_service(
+    mut events: EventReader<PickCard>,
+    service: Res<Service>,
+    screens: Query<Entity, With<ServiceScreen>>,
+    mut player: ResMut<Player>,
+    mut commands: Commands,
+) {
+    let pick = match events.iter().next() {
+        Some(pick) => pick,
+        None => return,
+    };
+
+    if let Some(card) = pick.0 {
+        service.apply(&mut player, card);
+    }
+
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands.insert_resource(NextState(MainState::Map));
+}
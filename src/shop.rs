@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    main_state::{Difficulty, MainState},
+    player::Player,
+    prefab::*,
+    rewards::{roll_rewards, Rarity, RewardCard},
+    run_config::RunConfig,
+    services::Service,
+    ui::*,
+};
+
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(OnClickPlugin::<ShopAction>::new())
+            .add_enter_system(MainState::Shop, open_shop)
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(MainState::Shop)
+                    .with_system(use_shop)
+                    .into(),
+            );
+    }
+}
+
+const SHOP_CARDS: usize = 4;
+const REMOVE_PRICE: u32 = 50;
+
+fn card_price(rarity: Rarity) -> u32 {
+    match rarity {
+        Rarity::Common => 30,
+        Rarity::Rare => 55,
+        Rarity::Epic => 85,
+    }
+}
+
+/// The cards for sale, sold ones are taken out.
+struct ShopOffer(Vec<Option<RewardCard>>);
+
+#[derive(Clone, Copy)]
+enum ShopAction {
+    /// Index of the card in the offer.
+    Buy(usize),
+    Remove,
+    Leave,
+}
+
+#[derive(Component)]
+struct ShopScreen;
+
+fn open_shop(
+    difficulty: Res<Difficulty>,
+    config: Res<RunConfig>,
+    player: Res<Player>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let cards = roll_rewards(
+        config.enemy_round(difficulty.round),
+        SHOP_CARDS,
+        &fastrand::Rng::new(),
+    );
+    let offer = ShopOffer(cards.into_iter().map(Some).collect());
+
+    spawn_shop_screen(
+        &offer,
+        &player,
+        asset_server.load("fonts/FiraMono-Medium.ttf"),
+        &mut commands,
+    );
+
+    commands.insert_resource(offer);
+}
+
+fn spawn_shop_screen(
+    offer: &ShopOffer,
+    player: &Player,
+    font: Handle<Font>,
+    commands: &mut Commands,
+) {
+    let mut children: Vec<Child> = vec![
+        TextPrefab {
+            text: "Shop".into(),
+            size: 60.0,
+            color: Color::WHITE,
+            font: font.clone(),
+        }
+        .into(),
+        TextPrefab {
+            text: format!("Gold {}", player.gold),
+            size: 40.0,
+            color: Color::GOLD,
+            font: font.clone(),
+        }
+        .into(),
+    ];
+
+    let button = |on_click: ShopAction, text: String, price: u32, color: Color| {
+        // what can't be afforded is still listed, just greyed out
+        let color = if player.gold >= price {
+            color
+        } else {
+            Color::GRAY
+        };
+
+        Child::from(ButtonPrefab {
+            on_click,
+            child: TextPrefab {
+                text: format!("{text} - {price} gold"),
+                size: 26.0,
+                color,
+                font: font.clone(),
+            },
+        })
+    };
+
+    children.extend(offer.0.iter().enumerate().filter_map(|(i, card)| {
+        let card = card.as_ref()?;
+
+        Some(button(
+            ShopAction::Buy(i),
+            card.description(),
+            card_price(card.rarity),
+            card.rarity.color(),
+        ))
+    }));
+
+    // the deck always needs enough cards for a full hand
+    if player.spells.len() > player.hand_size {
+        children.push(button(
+            ShopAction::Remove,
+            "Remove a card".into(),
+            REMOVE_PRICE,
+            Color::BLACK,
+        ));
+    }
+
+    children.push(
+        ButtonPrefab {
+            on_click: ShopAction::Leave,
+            child: TextPrefab {
+                text: "Leave".into(),
+                size: 30.0,
+                color: Color::BLACK,
+                font,
+            },
+        }
+        .into(),
+    );
+
+    commands
+        .spawn_prefab(FullScreen {
+            color: Color::BLACK,
+            child: VBox {
+                gap: 10.0,
+                children,
+            },
+        })
+        .insert(ShopScreen);
+
+    commands
+        .spawn_bundle(Camera3dBundle::default())
+        .insert(ShopScreen);
+}
+
+fn use_shop(
+    mut events: EventReader<ShopAction>,
+    mut offer: ResMut<ShopOffer>,
+    screens: Query<Entity, With<ShopScreen>>,
+    mut player: ResMut<Player>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let action = match events.iter().next() {
+        Some(action) => *action,
+        None => return,
+    };
+
+    let leave = match action {
+        ShopAction::Buy(i) => {
+            if let Some(card) = offer.0[i].take() {
+                let price = card_price(card.rarity);
+
+                if player.gold >= price {
+                    player.gold -= price;
+                    player.spells.push(card.spell);
+                } else {
+                    offer.0[i] = Some(card);
+                }
+            }
+            false
+        }
+        ShopAction::Remove if player.gold >= REMOVE_PRICE => {
+            commands.insert_resource(Service::Remove {
+                price: REMOVE_PRICE,
+            });
+            commands.insert_resource(NextState(MainState::Service));
+            true
+        }
+        ShopAction::Remove => false,
+        ShopAction::Leave => {
+            commands.insert_resource(NextState(MainState::Map));
+            true
+        }
+    };
+
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if leave {
+        commands.remove_resource::<ShopOffer>();
+    } else {
+        let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+        spawn_shop_screen(&offer, &player, font, &mut commands);
+    }
+}
@@ -0,0 +1,820 @@
+//! Plays whole runs without rendering anything, to check how winnable the game is.
+//! The battle rules come from the same code the game uses, only the board is replaced
+//! by a plain grid and the player by a [`Strategy`].
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
+
+use crate::{
+    board::{find_matches, Element, DEFAULT_MOVE_TIME, MIN_MOVE_TIME},
+    cards::draw_cards,
+    combat::{self, EnemyState, Field, MatchedGems, OVERHEAL_SHIELD},
+    enemies::{self, BoardHazard, EnemyDefinition},
+    main_state::Difficulty,
+    player::{Player, Spell, MAX_MERGE},
+    recipes::RecipeBook,
+    rewards::{self, RewardCard, REWARD_CHOICES},
+    run_config::RunConfig,
+};
+
+/// How many gems a player can move through per second while holding a gem.
+const SWAPS_PER_SECOND: f32 = 2.0;
+/// Battles taking longer than this are counted as a loss.
+const MAX_TURNS: u32 = 100;
+
+const WIDTH: usize = 6;
+const HEIGHT: usize = 5;
+
+/// The board, indexed by column then row with row 0 at the bottom.
+/// Gems are only missing while matches are being resolved.
+pub type Grid = [[Option<Element>; HEIGHT]; WIDTH];
+
+pub struct SimOptions {
+    pub runs: u32,
+    pub seed: u64,
+    pub strategies: Vec<StrategyKind>,
+    pub assets: PathBuf,
+    pub output: PathBuf,
+}
+
+impl Default for SimOptions {
+    fn default() -> Self {
+        Self {
+            runs: 1000,
+            seed: 0,
+            strategies: StrategyKind::iter().collect(),
+            assets: PathBuf::from("assets"),
+            output: PathBuf::from("sim_results"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum StrategyKind {
+    Random,
+    Greedy,
+    Solver,
+}
+
+impl StrategyKind {
+    fn build(self) -> Box<dyn Strategy> {
+        match self {
+            StrategyKind::Random => Box::new(RandomStrategy),
+            StrategyKind::Greedy => Box::new(GreedyStrategy),
+            StrategyKind::Solver => Box::new(SolverStrategy {
+                beam_width: 16,
+                max_depth: 10,
+            }),
+        }
+    }
+}
+
+/// Everything a strategy can look at when deciding its turn.
+pub struct Situation<'a> {
+    pub player: &'a Player,
+    pub enemy: &'a EnemyState,
+    pub field: &'a Field,
+    pub recipes: &'a RecipeBook,
+    pub max_swaps: usize,
+}
+
+/// The gem to pick up and the tiles it is dragged through, each next to the previous one.
+#[derive(Debug, Clone, Default)]
+pub struct Move {
+    pub start: (usize, usize),
+    pub path: Vec<(usize, usize)>,
+}
+
+/// Plays the player's side of a battle.
+pub trait Strategy {
+    /// Picks the indices of the cards in the hand to cast, in merge order, or none when no
+    /// selection can be cast.
+    fn pick_cards(
+        &mut self,
+        hand: &[Spell],
+        grid: &Grid,
+        situation: &Situation,
+        rng: &fastrand::Rng,
+    ) -> Option<Vec<usize>>;
+
+    /// Picks a move of at most [`Situation::max_swaps`] swaps.
+    fn pick_move(
+        &mut self,
+        grid: &Grid,
+        spell: &Spell,
+        situation: &Situation,
+        rng: &fastrand::Rng,
+    ) -> Move;
+
+    /// Picks a reward card to add to the deck, or none to skip. Takes the strongest by default.
+    fn pick_reward(&mut self, rewards: &[RewardCard], _: &fastrand::Rng) -> Option<usize> {
+        (0..rewards.len()).max_by_key(|&i| rewards[i].spell.attack + rewards[i].spell.heal)
+    }
+}
+
+/// Picks random cards and drags a random gem around randomly.
+struct RandomStrategy;
+
+impl Strategy for RandomStrategy {
+    fn pick_cards(
+        &mut self,
+        hand: &[Spell],
+        _: &Grid,
+        situation: &Situation,
+        rng: &fastrand::Rng,
+    ) -> Option<Vec<usize>> {
+        let mut selections = selections(hand, situation.player.energy);
+
+        (!selections.is_empty()).then(|| selections.swap_remove(rng.usize(..selections.len())))
+    }
+
+    fn pick_reward(&mut self, rewards: &[RewardCard], rng: &fastrand::Rng) -> Option<usize> {
+        let pick = rng.usize(..=rewards.len());
+
+        (pick < rewards.len()).then_some(pick)
+    }
+
+    fn pick_move(
+        &mut self,
+        _: &Grid,
+        _: &Spell,
+        situation: &Situation,
+        rng: &fastrand::Rng,
+    ) -> Move {
+        let start = (rng.usize(..WIDTH), rng.usize(..HEIGHT));
+        let mut path = vec![];
+
+        let mut current = start;
+        for _ in 0..rng.usize(1..=situation.max_swaps) {
+            let next = neighbours(current);
+            current = next[rng.usize(..next.len())];
+            path.push(current);
+        }
+
+        Move { start, path }
+    }
+}
+
+/// Tries every card selection with every single swap and keeps the best one.
+struct GreedyStrategy;
+
+impl Strategy for GreedyStrategy {
+    fn pick_cards(
+        &mut self,
+        hand: &[Spell],
+        grid: &Grid,
+        situation: &Situation,
+        _: &fastrand::Rng,
+    ) -> Option<Vec<usize>> {
+        selections(hand, situation.player.energy)
+            .into_iter()
+            .map(|cards| {
+                let spell = merge(situation.recipes, hand, &cards);
+                let score = best_swap(grid, &spell, situation).1;
+                (cards, score)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(cards, _)| cards)
+    }
+
+    fn pick_move(
+        &mut self,
+        grid: &Grid,
+        spell: &Spell,
+        situation: &Situation,
+        _: &fastrand::Rng,
+    ) -> Move {
+        best_swap(grid, spell, situation).0
+    }
+}
+
+fn best_swap(grid: &Grid, spell: &Spell, situation: &Situation) -> (Move, f32) {
+    positions()
+        .flat_map(|start| {
+            neighbours(start).into_iter().map(move |next| Move {
+                start,
+                path: vec![next],
+            })
+        })
+        .map(|x| {
+            let score = score(spell, &predict(grid, &x), situation);
+            (x, score)
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap()
+}
+
+/// Searches long moves with a beam search, only counting cascades made of gems already on the board.
+struct SolverStrategy {
+    beam_width: usize,
+    max_depth: usize,
+}
+
+impl SolverStrategy {
+    /// Finds the best move for any of the spells, returning the move and the index of the spell.
+    fn search(&self, grid: &Grid, spells: &[Spell], situation: &Situation) -> (Move, usize) {
+        let evaluate = |candidate: &Move| {
+            let matches = predict(grid, candidate);
+
+            spells
+                .iter()
+                .enumerate()
+                .map(|(i, spell)| (i, score(spell, &matches, situation)))
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .unwrap()
+        };
+
+        let mut beam: Vec<Move> = positions()
+            .map(|start| Move {
+                start,
+                path: vec![],
+            })
+            .collect();
+
+        let mut best = (Move::default(), 0, f32::MIN);
+
+        for _ in 0..situation.max_swaps.min(self.max_depth) {
+            let mut candidates: Vec<_> = beam
+                .iter()
+                .flat_map(|current| {
+                    let position = current.path.last().copied().unwrap_or(current.start);
+                    let previous = match current.path.len() {
+                        0 => None,
+                        1 => Some(current.start),
+                        n => Some(current.path[n - 2]),
+                    };
+
+                    neighbours(position)
+                        .into_iter()
+                        // going straight back undoes the last swap
+                        .filter(move |x| Some(*x) != previous)
+                        .map(move |next| {
+                            let mut path = current.path.clone();
+                            path.push(next);
+
+                            Move {
+                                start: current.start,
+                                path,
+                            }
+                        })
+                })
+                .map(|x| {
+                    let (spell, score) = evaluate(&x);
+                    (x, spell, score)
+                })
+                .collect();
+
+            candidates.sort_by(|(_, _, a), (_, _, b)| b.total_cmp(a));
+            candidates.truncate(self.beam_width);
+
+            if let Some(first) = candidates.first() {
+                if first.2 > best.2 {
+                    best = first.clone();
+                }
+            }
+
+            beam = candidates.into_iter().map(|(x, _, _)| x).collect();
+        }
+
+        (best.0, best.1)
+    }
+}
+
+impl Strategy for SolverStrategy {
+    fn pick_cards(
+        &mut self,
+        hand: &[Spell],
+        grid: &Grid,
+        situation: &Situation,
+        _: &fastrand::Rng,
+    ) -> Vec<usize> {
+        let mut selections = selections(hand, situation.player.energy);
+        let spells: Vec<_> = selections
+            .iter()
+            .map(|cards| merge(situation.recipes, hand, cards))
+            .collect();
+
+        selections.swap_remove(self.search(grid, &spells, situation).1)
+    }
+
+    fn pick_move(
+        &mut self,
+        grid: &Grid,
+        spell: &Spell,
+        situation: &Situation,
+        _: &fastrand::Rng,
+    ) -> Move {
+        self.search(grid, std::slice::from_ref(spell), situation).0
+    }
+}
+
+/// Damage dealt plus health actually restored and shield gained.
+fn score(spell: &Spell, matches: &[MatchedGems], situation: &Situation) -> f32 {
+    let enemy = situation.enemy;
+    let player = situation.player;
+
+    let damage = combat::spell_damage(
+        spell,
+        matches,
+        &enemy.statuses,
+        enemy.definition.affinity,
+        situation.field,
+    )
+    .total()
+    .min(enemy.current_health);
+    let heal = combat::spell_heal(spell, matches, &player.statuses).amount;
+    let healed = heal.min(player.max_health - player.current_health);
+    let overheal = ((heal - healed) as f32 * OVERHEAL_SHIELD).round() as u32;
+    let shield = combat::spell_shield(spell, matches).amount + overheal;
+
+    (damage + healed + shield) as f32
+}
+
+/// Every set of cards in the hand that can be cast together with the energy, in hand order.
+fn selections(hand: &[Spell], energy: u32) -> Vec<Vec<usize>> {
+    let mut selections: Vec<Vec<usize>> = vec![vec![]];
+
+    for i in 0..hand.len() {
+        for j in 0..selections.len() {
+            if selections[j].len() < MAX_MERGE {
+                let mut selection = selections[j].clone();
+                selection.push(i);
+                selections.push(selection);
+            }
+        }
+    }
+
+    selections.retain(|cards| {
+        let spells: Vec<_> = cards.iter().map(|&i| &hand[i]).collect();
+        Spell::can_merge(&spells) && spells.iter().map(|x| x.cost).sum::<u32>() <= energy
+    });
+
+    selections
+}
+
+fn merge(recipes: &RecipeBook, hand: &[Spell], cards: &[usize]) -> Spell {
+    let spells: Vec<_> = cards.iter().map(|&i| &hand[i]).collect();
+
+    recipes.merge(&spells)
+}
+
+fn positions() -> impl Iterator<Item = (usize, usize)> {
+    (0..WIDTH).flat_map(|x| (0..HEIGHT).map(move |y| (x, y)))
+}
+
+fn neighbours((x, y): (usize, usize)) -> Vec<(usize, usize)> {
+    let mut neighbours = Vec::with_capacity(4);
+
+    if x > 0 {
+        neighbours.push((x - 1, y));
+    }
+    if x + 1 < WIDTH {
+        neighbours.push((x + 1, y));
+    }
+    if y > 0 {
+        neighbours.push((x, y - 1));
+    }
+    if y + 1 < HEIGHT {
+        neighbours.push((x, y + 1));
+    }
+
+    neighbours
+}
+
+fn random_grid(field: &Field, rng: &fastrand::Rng) -> Grid {
+    let mut grid = [[None; HEIGHT]; WIDTH];
+    for gem in grid.iter_mut().flatten() {
+        *gem = Some(field.skyfall(rng));
+    }
+
+    grid
+}
+
+fn apply_move(grid: &mut Grid, candidate: &Move) {
+    let (mut x, mut y) = candidate.start;
+
+    for &(next_x, next_y) in &candidate.path {
+        let held = grid[x][y];
+        grid[x][y] = grid[next_x][next_y];
+        grid[next_x][next_y] = held;
+
+        (x, y) = (next_x, next_y);
+    }
+}
+
+/// Removes matches until there are none left. New gems only fall in when there is a field
+/// and an rng to pick them with, otherwise the spaces are left empty.
+fn resolve(grid: &mut Grid, skyfall: Option<(&Field, &fastrand::Rng)>) -> Vec<MatchedGems> {
+    let mut matches = vec![];
+
+    loop {
+        let found = find_matches(grid);
+        if found.is_empty() {
+            return matches;
+        }
+
+        for gem_match in found {
+            for &(x, y) in &gem_match.positions {
+                grid[x][y] = None;
+            }
+
+            matches.push(MatchedGems {
+                element: gem_match.element,
+                count: gem_match.positions.len() as u32,
+            });
+        }
+
+        for column in grid.iter_mut() {
+            let remaining: Vec<_> = column.iter().flatten().copied().collect();
+            let mut remaining = remaining.into_iter();
+
+            *column = [(); HEIGHT].map(|_| {
+                remaining
+                    .next()
+                    .or_else(|| skyfall.map(|(field, rng)| field.skyfall(rng)))
+            });
+        }
+    }
+}
+
+fn max_swaps(move_time: f32) -> usize {
+    ((move_time.max(MIN_MOVE_TIME) * SWAPS_PER_SECOND) as usize).max(1)
+}
+
+/// Turns up to `count` random gems the filter takes into the element.
+fn convert_gems(
+    grid: &mut Grid,
+    filter: impl Fn(Option<Element>) -> bool,
+    element: Element,
+    count: u32,
+    rng: &fastrand::Rng,
+) {
+    let mut candidates: Vec<_> = positions().filter(|&(x, y)| filter(grid[x][y])).collect();
+
+    rng.shuffle(&mut candidates);
+
+    for (x, y) in candidates.into_iter().take(count as usize) {
+        grid[x][y] = Some(element);
+    }
+}
+
+/// The matches a move makes without knowing which gems will fall in.
+fn predict(grid: &Grid, candidate: &Move) -> Vec<MatchedGems> {
+    let mut grid = *grid;
+    apply_move(&mut grid, candidate);
+
+    resolve(&mut grid, None)
+}
+
+struct Deck {
+    draw_pile: Vec<Spell>,
+    discard_pile: Vec<Spell>,
+}
+
+impl Deck {
+    fn new(spells: &[Spell], rng: &fastrand::Rng) -> Self {
+        let mut draw_pile = spells.to_vec();
+        rng.shuffle(&mut draw_pile);
+
+        Self {
+            draw_pile,
+            discard_pile: vec![],
+        }
+    }
+
+    fn draw(&mut self, count: usize, rng: &fastrand::Rng) -> Vec<Spell> {
+        draw_cards(&mut self.draw_pile, &mut self.discard_pile, count, |x| {
+            rng.shuffle(x)
+        })
+    }
+}
+
+struct TurnRecord {
+    round: u32,
+    damage_dealt: u32,
+    damage_taken: u32,
+}
+
+enum Outcome {
+    Won,
+    Died(u32),
+    /// The battle in the given round went on for longer than [`MAX_TURNS`].
+    Stalled(u32),
+}
+
+struct RunResult {
+    seed: u64,
+    outcome: Outcome,
+    rounds_cleared: u32,
+    healed: u32,
+    turns: Vec<TurnRecord>,
+}
+
+fn simulate_run(
+    definitions: &[EnemyDefinition],
+    recipes: &RecipeBook,
+    strategy: &mut dyn Strategy,
+    seed: u64,
+) -> RunResult {
+    let rng = fastrand::Rng::with_seed(seed);
+
+    let mut player = Player::default();
+    let mut difficulty = Difficulty::default();
+    // only standard runs are simulated, the map isn't either
+    let config = RunConfig::default();
+    let mut result = RunResult {
+        seed,
+        outcome: Outcome::Won,
+        rounds_cleared: 0,
+        healed: 0,
+        turns: vec![],
+    };
+
+    while difficulty.round <= config.rounds {
+        let round = difficulty.round;
+        let enemy_round = config.enemy_round(round);
+
+        let definition = if config.is_boss_round(round) {
+            enemies::random_boss(definitions, enemy_round, &rng)
+        } else {
+            enemies::random_enemy(definitions, enemy_round, &rng)
+        };
+
+        let (max_health, attack) = difficulty.enemy_stats(definition);
+        let mut enemy = EnemyState::new(definition.clone(), max_health, attack);
+        // environments are not simulated, only the difficulty adds field effects, which
+        // also weigh the skyfall like on the real board
+        let field = Field {
+            effects: difficulty.field_effects(),
+        };
+
+        player.start_battle();
+        let mut deck = Deck::new(&player.spells, &rng);
+        let mut grid = random_grid(&field, &rng);
+
+        let mut won = false;
+        for _ in 0..MAX_TURNS {
+            player.decay_shield();
+            player.refill_energy();
+
+            let mut move_time = DEFAULT_MOVE_TIME - difficulty.move_time_penalty();
+            match enemy.hazard() {
+                Some(BoardHazard::Corrupt { element, gems }) => {
+                    convert_gems(&mut grid, |x| x != Some(element), element, gems, &rng);
+                }
+                Some(BoardHazard::Haste { seconds }) => move_time -= seconds,
+                None => {}
+            }
+
+            let hand = deck.draw(player.take_draw_count(difficulty.hand_size_penalty()), &rng);
+
+            let mut situation = Situation {
+                player: &player,
+                enemy: &enemy,
+                field: &field,
+                recipes,
+                max_swaps: max_swaps(move_time),
+            };
+
+            // drained players can be left without the energy for any card, the turn then
+            // passes without a cast or a move
+            let damage_dealt = match strategy.pick_cards(&hand, &grid, &situation, &rng) {
+                Some(cards) => {
+                    let spell = merge(recipes, &hand, &cards);
+
+                    let cast = combat::spell_cast(&spell);
+                    situation.max_swaps = max_swaps(move_time + cast.move_time);
+                    for (from, to, gems) in cast.conversions {
+                        convert_gems(&mut grid, |x| x == Some(from), to, gems, &rng);
+                    }
+
+                    let mut player_move = strategy.pick_move(&grid, &spell, &situation, &rng);
+                    player_move.path.truncate(situation.max_swaps);
+                    player.extra_draws += cast.draws as usize;
+                    player.energy -= spell.cost;
+
+                    apply_move(&mut grid, &player_move);
+                    let matches = resolve(&mut grid, Some((&field, &rng)));
+
+                    let damage_dealt = enemy.take_spell(&spell, &matches, &field).total();
+
+                    let heal = combat::spell_heal(&spell, &matches, &player.statuses);
+                    let (healed, _) = player.heal(heal.amount);
+                    for status in heal.applied_statuses {
+                        player.statuses.apply(status);
+                    }
+                    player.shield += combat::spell_shield(&spell, &matches).amount;
+                    result.healed += healed;
+
+                    damage_dealt
+                }
+                None => 0,
+            };
+
+            deck.discard_pile.extend(hand);
+
+            let mut record = TurnRecord {
+                round,
+                damage_dealt,
+                damage_taken: 0,
+            };
+
+            if enemy.current_health == 0 {
+                result.turns.push(record);
+                won = true;
+                break;
+            }
+
+            let turn = enemy.take_turn(&player.statuses);
+            if let Some(damage) = turn.damage {
+                record.damage_taken = player.take_damage(damage);
+            }
+
+            player.statuses.tick();
+            for status in turn.applied_statuses {
+                player.statuses.apply(status);
+            }
+
+            result.turns.push(record);
+
+            if player.current_health == 0 {
+                result.outcome = Outcome::Died(round);
+                return result;
+            }
+        }
+
+        if !won {
+            result.outcome = Outcome::Stalled(round);
+            return result;
+        }
+
+        result.rounds_cleared += 1;
+        difficulty.next_round(config.pace());
+
+        if difficulty.round <= config.rounds {
+            let rewards =
+                rewards::roll_rewards(config.enemy_round(difficulty.round), REWARD_CHOICES, &rng);
+
+            if let Some(pick) = strategy.pick_reward(&rewards, &rng) {
+                player.spells.push(rewards[pick].spell.clone());
+            }
+        }
+    }
+
+    result
+}
+
+pub fn run(options: &SimOptions) -> anyhow::Result<()> {
+    let definitions = enemies::read_roster_file(&options.assets, enemies::ROSTER_PATH)?;
+    anyhow::ensure!(!definitions.is_empty(), "the enemy roster is empty");
+    let recipes = RecipeBook::read_file(&options.assets)?;
+
+    std::fs::create_dir_all(&options.output)
+        .with_context(|| format!("failed to create {}", options.output.display()))?;
+
+    let mut runs = String::from(
+        "strategy,seed,won,rounds_cleared,death_round,stalled,turns,damage_dealt,damage_taken,healed\n",
+    );
+    let mut summary =
+        String::from("strategy,runs,wins,win_rate,mean_rounds_cleared,mean_turns_per_round\n");
+    let mut deaths = String::from("strategy,round,deaths,stalls,share\n");
+    let mut damage = String::from("strategy,round,kind,samples,mean,min,p10,p50,p90,max\n");
+
+    for &kind in &options.strategies {
+        let mut strategy = kind.build();
+
+        let results: Vec<_> = (0..options.runs as u64)
+            .map(|i| simulate_run(&definitions, &recipes, strategy.as_mut(), options.seed + i))
+            .collect();
+
+        for result in &results {
+            let (death_round, stalled) = match result.outcome {
+                Outcome::Won => (String::new(), false),
+                Outcome::Died(round) => (round.to_string(), false),
+                Outcome::Stalled(round) => (round.to_string(), true),
+            };
+
+            writeln!(
+                runs,
+                "{kind},{},{},{},{death_round},{stalled},{},{},{},{}",
+                result.seed,
+                matches!(result.outcome, Outcome::Won),
+                result.rounds_cleared,
+                result.turns.len(),
+                result.turns.iter().map(|x| x.damage_dealt).sum::<u32>(),
+                result.turns.iter().map(|x| x.damage_taken).sum::<u32>(),
+                result.healed,
+            )?;
+        }
+
+        let wins = results
+            .iter()
+            .filter(|x| matches!(x.outcome, Outcome::Won))
+            .count();
+        let rounds_cleared: u32 = results.iter().map(|x| x.rounds_cleared).sum();
+        let turns: usize = results.iter().map(|x| x.turns.len()).sum();
+        let rounds_played: usize = results
+            .iter()
+            .map(|x| x.turns.iter().map(|x| x.round).max().unwrap_or_default() as usize)
+            .sum();
+
+        writeln!(
+            summary,
+            "{kind},{},{wins},{:.4},{:.3},{:.3}",
+            results.len(),
+            wins as f32 / results.len().max(1) as f32,
+            rounds_cleared as f32 / results.len().max(1) as f32,
+            turns as f32 / rounds_played.max(1) as f32,
+        )?;
+
+        println!(
+            "{kind}: won {wins} of {} runs ({:.1}%)",
+            results.len(),
+            100.0 * wins as f32 / results.len().max(1) as f32
+        );
+
+        for round in 1..=RunConfig::default().rounds {
+            let died = results
+                .iter()
+                .filter(|x| matches!(x.outcome, Outcome::Died(r) if r == round))
+                .count();
+            let stalled = results
+                .iter()
+                .filter(|x| matches!(x.outcome, Outcome::Stalled(r) if r == round))
+                .count();
+
+            writeln!(
+                deaths,
+                "{kind},{round},{died},{stalled},{:.4}",
+                (died + stalled) as f32 / results.len().max(1) as f32
+            )?;
+
+            let turns: Vec<_> = results
+                .iter()
+                .flat_map(|x| &x.turns)
+                .filter(|x| x.round == round)
+                .collect();
+
+            for (name, samples) in [
+                (
+                    "dealt",
+                    turns.iter().map(|x| x.damage_dealt).collect::<Vec<_>>(),
+                ),
+                (
+                    "taken",
+                    turns.iter().map(|x| x.damage_taken).collect::<Vec<_>>(),
+                ),
+            ] {
+                if let Some(row) = distribution(samples) {
+                    writeln!(damage, "{kind},{round},{name},{row}")?;
+                }
+            }
+        }
+    }
+
+    for (name, contents) in [
+        ("runs.csv", runs),
+        ("summary.csv", summary),
+        ("deaths.csv", deaths),
+        ("damage.csv", damage),
+    ] {
+        write_file(&options.output.join(name), &contents)?;
+    }
+
+    println!("results written to {}", options.output.display());
+
+    Ok(())
+}
+
+/// `samples,mean,min,p10,p50,p90,max` of the samples.
+fn distribution(mut samples: Vec<u32>) -> Option<String> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    samples.sort_unstable();
+
+    let percentile = |p: f32| samples[((samples.len() - 1) as f32 * p).round() as usize];
+    let mean = samples.iter().sum::<u32>() as f32 / samples.len() as f32;
+
+    Some(format!(
+        "{},{mean:.2},{},{},{},{},{}",
+        samples.len(),
+        samples[0],
+        percentile(0.1),
+        percentile(0.5),
+        percentile(0.9),
+        samples[samples.len() - 1],
+    ))
+}
+
+fn write_file(path: &Path, contents: &str) -> anyhow::Result<()> {
+    std::fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
@@ -89,6 +89,8 @@ fn add_materials(
 #[derive(Default)]
 pub struct Loading {
     pub assets: Vec<HandleUntyped>,
+    /// Problems that keep the game from starting, shown instead of waiting forever.
+    pub errors: Vec<String>,
 }
 
 #[derive(Component, Default)]
@@ -403,6 +405,29 @@ fn update_world_cursors(
     }
 }
 
+/// Maps a position seen by one camera onto the world of another camera,
+/// e.g. to place 2d text over something rendered by a 3d camera.
+pub fn project_between_cameras(
+    (from_camera, from_transform): (&Camera, &GlobalTransform),
+    (to_camera, to_transform): (&Camera, &GlobalTransform),
+    position: Vec3,
+) -> Option<Vec2> {
+    let world_to_ndc = from_camera.projection_matrix() * from_transform.compute_matrix().inverse();
+    let ndc = world_to_ndc.project_point3(position);
+
+    if !ndc.is_finite() {
+        return None;
+    }
+
+    let ndc_to_world = to_transform.compute_matrix() * to_camera.projection_matrix().inverse();
+
+    Some(
+        ndc_to_world
+            .project_point3(ndc.truncate().extend(-1.0))
+            .truncate(),
+    )
+}
+
 #[derive(Debug, Clone)]
 pub struct WorldCursorEvent {
     pub entity: Entity,